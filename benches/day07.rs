@@ -1,19 +1,23 @@
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use aoc_2025::counting::Checked;
 use aoc_2025::{input_path, read_to_string};
 
 use crate::day07::ChristmasTree;
 
 // Reuse src/bin/day03.rs as a module
 #[path = "../src/bin/day07.rs"]
+#[allow(dead_code)]
 mod day07;
 
 fn bench_part2(c: &mut Criterion) {
     // Load your real AoC input once
     let input = read_to_string(input_path(7)).unwrap();
- let mut christmas_tree = ChristmasTree::new(&input);
+ let christmas_tree = ChristmasTree::new(&input);
     c.bench_function("day07_part2", |b| {
         b.iter(|| {
-            let res = day07::part2(black_box(&christmas_tree)).unwrap();
+            let res = day07::part2(black_box(&christmas_tree), &Checked::<i128>::new()).unwrap();
             black_box(res);
         })
     });
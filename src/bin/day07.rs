@@ -1,8 +1,12 @@
 use std::collections::HashMap;
 
-use anyhow::Result;
+use std::fmt;
+
+use anyhow::{Context, Result};
+use aoc_2025::counting::{Checked, Counter, Exact, Modular, Overflow};
 use aoc_2025::{input_path, lines, read_to_string};
-use std::sync::{ Mutex};
+use rayon::join;
+use std::sync::Mutex;
 
 fn main() -> Result<()> {
     let input = read_to_string(input_path(7))?;
@@ -11,7 +15,29 @@ fn main() -> Result<()> {
     let mut christmas_tree = ChristmasTree::new(&input);
     println!("Part 1: {}", part1(&mut christmas_tree)?);
     christmas_tree.reset();
-    println!("Part 2: {}", part2(&mut christmas_tree)?);
+
+    // timelines are counted in `i128` and fail on overflow, unless `--exact`
+    // asks for big integers or `--modulo <p>` reduces them modulo a prime
+    let args: Vec<String> = std::env::args().collect();
+    if args.iter().any(|a| a == "--exact") {
+        return report_part2(&christmas_tree, &Exact);
+    }
+    if let Some(pos) = args.iter().position(|a| a == "--modulo") {
+        let p: u64 = args
+            .get(pos + 1)
+            .and_then(|p| p.parse().ok())
+            .context("--modulo takes a prime")?;
+        let counter = Modular::new(p).with_context(|| format!("--modulo {p} is not prime"))?;
+        return report_part2(&christmas_tree, &counter);
+    }
+    report_part2(&christmas_tree, &Checked::<i128>::new())
+}
+
+fn report_part2<C: Counter>(christmas_tree: &ChristmasTree, counter: &C) -> Result<()>
+where
+    C::Value: fmt::Display,
+{
+    println!("Part 2: {}", part2(christmas_tree, counter)?);
     Ok(())
 }
 
@@ -69,7 +95,17 @@ fn draw_lines(christmas_tree: &mut ChristmasTree, start_x: usize, start_y: usize
     splits
 }
 
-pub fn part2(christmas_tree: &ChristmasTree) -> Result<i128> {
+pub fn part2<C: Counter>(christmas_tree: &ChristmasTree, counter: &C) -> Result<C::Value> {
+    Ok(count_all_timelines(christmas_tree, counter)?)
+}
+
+/// Counts timelines using the arithmetic of `counter`, e.g. `Exact` for
+/// arbitrary precision or `Modular` for counts modulo a prime. Fixed-width
+/// counters return `Overflow` rather than wrapping.
+pub fn count_all_timelines<C: Counter>(
+    christmas_tree: &ChristmasTree,
+    counter: &C,
+) -> Result<C::Value, Overflow> {
     let mut start: Option<(usize, usize)> = None;
 
     'outer: for x in 0..christmas_tree.columns.len() {
@@ -81,37 +117,32 @@ pub fn part2(christmas_tree: &ChristmasTree) -> Result<i128> {
         }
     }
 
-    let memo: Memo = Mutex::new(HashMap::new());
+    let memo: Memo<C::Value> = Mutex::new(HashMap::new());
 
-    let answer = if let Some((sx, sy)) = start {
-        count_timelines(christmas_tree, &memo, sx, sy)
-    } else {
-        0
-    };
-
-    Ok(answer)
+    match start {
+        Some((sx, sy)) => count_timelines(christmas_tree, counter, &memo, sx, sy),
+        None => Ok(counter.zero()),
+    }
 }
 
+type Memo<V> = Mutex<HashMap<(usize, usize), V>>;
 
-type Memo = Mutex<HashMap<(usize, usize), i128>>;
-
-use rayon::join;
-
-fn count_timelines(
+fn count_timelines<C: Counter>(
     christmas_tree: &ChristmasTree,
-    memo: &Memo,
+    counter: &C,
+    memo: &Memo<C::Value>,
     start_x: usize,
     start_y: usize,
-) -> i128 {
+) -> Result<C::Value, Overflow> {
     {
         let memo_guard = memo.lock().unwrap();
-        if let Some(&cached) = memo_guard.get(&(start_x, start_y)) {
-            return cached;
+        if let Some(cached) = memo_guard.get(&(start_x, start_y)) {
+            return Ok(cached.clone());
         }
     }
 
     let height = christmas_tree.columns[start_x].characters.len();
-    let mut result = 1; 
+    let mut result = counter.one();
 
     for y in start_y..height {
         let c = christmas_tree.columns[start_x].characters[y];
@@ -132,14 +163,14 @@ fn count_timelines(
             result = match (left, right) {
                 (Some((lx, ly)), Some((rx, ry))) => {
                     let (l, r) = join(
-                        || count_timelines(christmas_tree, memo, lx, ly),
-                        || count_timelines(christmas_tree, memo, rx, ry),
+                        || count_timelines(christmas_tree, counter, memo, lx, ly),
+                        || count_timelines(christmas_tree, counter, memo, rx, ry),
                     );
-                    l + r
+                    counter.add(&l?, &r?)?
                 }
-                (Some((lx, ly)), None) => count_timelines(christmas_tree, memo, lx, ly),
-                (None, Some((rx, ry))) => count_timelines(christmas_tree, memo, rx, ry),
-                (None, None) => 0,
+                (Some((lx, ly)), None) => count_timelines(christmas_tree, counter, memo, lx, ly)?,
+                (None, Some((rx, ry))) => count_timelines(christmas_tree, counter, memo, rx, ry)?,
+                (None, None) => counter.zero(),
             };
 
             break;
@@ -147,20 +178,19 @@ fn count_timelines(
     }
     {
         let mut memo_guard = memo.lock().unwrap();
-        memo_guard.insert((start_x, start_y), result);
+        memo_guard.insert((start_x, start_y), result.clone());
     }
 
-    result
+    Ok(result)
 }
 
-
 pub struct ChristmasTree {
     columns: Vec<Column>,
 }
 
 impl ChristmasTree {
-    pub fn new(input: &String) -> Self {
-        let input_lines = lines(&input);
+    pub fn new(input: &str) -> Self {
+        let input_lines = lines(input);
         let mut columns: Vec<Column> = Vec::new();
         for line in input_lines {
             for (i, char) in line.chars().enumerate() {
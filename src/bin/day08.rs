@@ -1,186 +1,173 @@
-use std::mem::discriminant;
+use std::fs::File;
+use std::io::{BufWriter, Write};
 
-use anyhow::Result;
-use aoc_2025::{input_path, input_path_test, lines, read_to_string};
+use anyhow::{Context, Result, bail};
+use aoc_2025::disjoint_set::DisjointSet;
+use aoc_2025::spatial::KdTree;
+use aoc_2025::{input_path, lines, read_to_string};
 
 fn main() -> Result<()> {
     let input = read_to_string(input_path(8))?;
-    let mut junction_boxes: Vec<JunctionBox> = Vec::new();
-    for line in lines(&input) {
-        let coordinate: Vec<&str> = line.split(',').collect();
-        junction_boxes.push(JunctionBox {
-            x: coordinate[0].parse::<f32>().unwrap(),
-            y: coordinate[1].parse::<f32>().unwrap(),
-            z: coordinate[2].parse::<f32>().unwrap(),
-        });
-    }
-    println!("Part 1: {}", part1(&junction_boxes)?);
-    println!("Part 2: {}", part2(&junction_boxes)?);
-    Ok(())
-}
-
-fn part1(junction_boxes: &Vec<JunctionBox>) -> Result<f32> {
-    let n = junction_boxes.len();
-
-    let mut edges: Vec<Edge> = Vec::new();
-    for i in 0..n {
-        for j in (i + 1)..n {
-            let d = junction_boxes[i].distance_to(&junction_boxes[j]);
-            edges.push(Edge {
-                a: i,
-                b: j,
-                dist: d,
-            });
-        }
+    let junction_boxes = parse_junction_boxes(&input)?;
+    let timeline = ConnectionTimeline::new(&junction_boxes);
+    println!("Part 1: {}", part1(&junction_boxes, 1000, 3)?);
+    println!("Part 2: {}", part2(&junction_boxes, &timeline)?);
+
+    // `--timeline <file>` exports every successful connection as CSV,
+    // `--largest <n>` reports when a circuit first reaches n boxes
+    let args: Vec<String> = std::env::args().collect();
+    let flag = |name: &str| args.iter().position(|a| a == name).map(|i| args.get(i + 1));
+
+    if let Some(path) = flag("--timeline") {
+        let path = path.context("--timeline needs a file path")?;
+        timeline.write_csv(BufWriter::new(File::create(path)?))?;
+        println!("Wrote {} connections to {path}", timeline.events.len());
     }
-
-    edges.sort_by(|e1, e2| e1.dist.partial_cmp(&e2.dist).unwrap());
-
-    let k = 1000;
-    let mut circuits: Vec<Circuit> = Vec::new();
-
-    for edge in edges.into_iter().take(k) {
-        let a = edge.a;
-        let b = edge.b;
-
-        let idx_a = find_circuit_index(&circuits, a);
-        let idx_b = find_circuit_index(&circuits, b);
-
-        match (idx_a, idx_b) {
-            (None, None) => {
-                circuits.push(Circuit {
-                    members: vec![a, b],
-                });
-            }
-            (Some(i), None) => {
-                circuits[i].members.push(b);
-            }
-            (None, Some(i)) => {
-                circuits[i].members.push(a);
-            }
-            (Some(i), Some(j)) if i != j => {
-                let (keep, remove) = if i < j { (i, j) } else { (j, i) };
-                let mut other = circuits.remove(remove);
-                circuits[keep].members.append(&mut other.members);
-            }
-            _ => {}
+    if let Some(size) = flag("--largest") {
+        let size: usize = size.context("--largest needs a circuit size")?.parse()?;
+        match timeline.first_with_largest_at_least(size) {
+            Some(e) => println!(
+                "Largest circuit reaches {size} boxes after connection {} ({} - {})",
+                e.step, e.a, e.b
+            ),
+            None => println!("No circuit ever reaches {size} boxes"),
         }
     }
+    Ok(())
+}
 
-    let mut sizes: Vec<usize> = circuits.iter().map(|c| c.members.len()).collect();
-
-    let mut in_any: Vec<bool> = vec![false; n];
-    for c in &circuits {
-        for &idx in &c.members {
-            in_any[idx] = true;
-        }
+fn parse_junction_boxes(input: &str) -> Result<Vec<JunctionBox>> {
+    let mut junction_boxes: Vec<JunctionBox> = Vec::new();
+    for line in lines(input) {
+        let coordinate = line
+            .split(',')
+            .map(|n| n.trim().parse::<i64>())
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("invalid junction box: {line}"))?;
+        let [x, y, z] = coordinate[..] else {
+            bail!("expected three coordinates: {line}");
+        };
+        junction_boxes.push(JunctionBox { x, y, z });
     }
-    let singletons = in_any.iter().filter(|&&b| !b).count();
-    sizes.extend(std::iter::repeat(1).take(singletons));
-
-    sizes.sort_unstable_by(|a, b| b.cmp(a));
-    let answer = sizes[0] * sizes[1] * sizes[2];
-    Ok(answer as f32)
+    Ok(junction_boxes)
 }
 
-fn find_circuit_index(circuits: &Vec<Circuit>, idx: usize) -> Option<usize> {
-    circuits.iter().position(|c| c.members.contains(&idx))
+fn kd_tree(junction_boxes: &[JunctionBox]) -> KdTree<3> {
+    KdTree::new(junction_boxes.iter().map(JunctionBox::position).collect())
 }
 
-fn part2(junction_boxes: &Vec<JunctionBox>) -> Result<i64> {
-    let n = junction_boxes.len();
+/// Joins the `connections` closest pairs of boxes and multiplies the sizes
+/// of the `largest` biggest circuits.
+fn part1(junction_boxes: &[JunctionBox], connections: usize, largest: usize) -> Result<usize> {
+    let tree = kd_tree(junction_boxes);
+    let mut circuits = DisjointSet::new(junction_boxes.len());
 
-    let mut edges: Vec<Edge> = Vec::new();
-    for i in 0..n {
-        for j in (i + 1)..n {
-            let d = junction_boxes[i].distance_to(&junction_boxes[j]);
-            edges.push(Edge { a: i, b: j, dist: d });
-        }
+    for pair in tree.nearest_pairs().take(connections) {
+        circuits.union(pair.a, pair.b);
     }
 
-    edges.sort_by(|e1, e2| e1.dist.partial_cmp(&e2.dist).unwrap());
-
-    let mut dsu = DSU::new(n);
-    let mut answer: Option<i64> = None;
-
-    for edge in edges {
-        if dsu.union(edge.a, edge.b) {
-            if dsu.components == 1 {
-                let xa = junction_boxes[edge.a].x as i64;
-                let xb = junction_boxes[edge.b].x as i64;
-                answer = Some(xa * xb);
-                break;
-            }
-        }
+    let sizes = circuits.top_sizes(largest);
+    if sizes.len() < largest {
+        bail!("need at least {largest} circuits, found {}", sizes.len());
     }
-
-    Ok(answer.expect("Graph never became fully connected"))
+    Ok(sizes.iter().product())
 }
 
-
-#[derive(Debug)]
-struct Circuit {
-    members: Vec<usize>,
+fn part2(junction_boxes: &[JunctionBox], timeline: &ConnectionTimeline) -> Result<i128> {
+    match timeline.events.last() {
+        Some(last) if last.components == 1 => {
+            let xa = junction_boxes[last.a].x;
+            let xb = junction_boxes[last.b].x;
+            Ok(xa as i128 * xb as i128)
+        }
+        _ => bail!("Graph never became fully connected"),
+    }
 }
 
-#[derive(Debug)]
-struct Edge {
+/// A connection that merged two circuits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct ConnectionEvent {
+    /// 1-based count of successful connections so far.
+    step: usize,
     a: usize,
     b: usize,
-    dist: f32,
-}
-
-#[derive(Clone, Copy, Debug, PartialEq)]
-struct JunctionBox {
-    x: f32,
-    y: f32,
-    z: f32,
-}
-
-impl JunctionBox {
-    fn distance_to(&self, other: &JunctionBox) -> f32 {
-        ((self.x - other.x).powi(2) + (self.y - other.y).powi(2) + (self.z - other.z).powi(2))
-            .sqrt()
-    }
+    dist_sq: i128,
+    /// Circuits left after this connection.
+    components: usize,
+    /// Size of the largest circuit after this connection.
+    largest: usize,
 }
 
-struct DSU {
-    parent: Vec<usize>,
-    size: Vec<usize>,
-    components: usize,
+/// Every connection that merges two circuits, in the order they happen when
+/// boxes are joined closest first. Connections within a circuit change
+/// nothing and are left out.
+struct ConnectionTimeline {
+    events: Vec<ConnectionEvent>,
 }
 
-impl DSU {
-    fn new(n: usize) -> Self {
-        DSU {
-            parent: (0..n).collect(),
-            size: vec![1; n],
-            components: n,
+impl ConnectionTimeline {
+    fn new(junction_boxes: &[JunctionBox]) -> Self {
+        let n = junction_boxes.len();
+
+        // the spanning tree holds exactly the edges that merge two circuits
+        let mut edges = kd_tree(junction_boxes).minimum_spanning_tree();
+        edges.sort_unstable();
+
+        let mut circuits = DisjointSet::new(n);
+        let mut largest = usize::from(n > 0);
+        let mut events = Vec::with_capacity(edges.len());
+
+        for edge in edges {
+            circuits.union(edge.a, edge.b);
+            largest = largest.max(circuits.size_of(edge.a));
+            events.push(ConnectionEvent {
+                step: events.len() + 1,
+                a: edge.a,
+                b: edge.b,
+                dist_sq: edge.dist_sq,
+                components: circuits.component_count(),
+                largest,
+            });
         }
+
+        ConnectionTimeline { events }
     }
 
-    fn find(&mut self, x: usize) -> usize {
-        if self.parent[x] != x {
-            let root = self.find(self.parent[x]);
-            self.parent[x] = root;
-        }
-        self.parent[x]
+    /// The first connection after which some circuit holds at least `size` boxes.
+    fn first_with_largest_at_least(&self, size: usize) -> Option<&ConnectionEvent> {
+        let idx = self.events.partition_point(|e| e.largest < size);
+        self.events.get(idx)
     }
-    fn union(&mut self, a: usize, b: usize) -> bool {
-        let mut ra = self.find(a);
-        let mut rb = self.find(b);
 
-        if ra == rb {
-            return false;
+    fn write_csv<W: Write>(&self, mut out: W) -> Result<()> {
+        writeln!(out, "step,a,b,dist_sq,distance,components,largest")?;
+        for e in &self.events {
+            writeln!(
+                out,
+                "{},{},{},{},{:.3},{},{}",
+                e.step,
+                e.a,
+                e.b,
+                e.dist_sq,
+                (e.dist_sq as f64).sqrt(),
+                e.components,
+                e.largest
+            )?;
         }
+        out.flush()?;
+        Ok(())
+    }
+}
 
-        if self.size[ra] < self.size[rb] {
-            std::mem::swap(&mut ra, &mut rb);
-        }
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct JunctionBox {
+    x: i64,
+    y: i64,
+    z: i64,
+}
 
-        self.parent[rb] = ra;
-        self.size[ra] += self.size[rb];
-        self.components -= 1;
-        true
+impl JunctionBox {
+    fn position(&self) -> [i64; 3] {
+        [self.x, self.y, self.z]
     }
 }
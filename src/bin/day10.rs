@@ -1,31 +1,118 @@
-use std::{collections::HashMap, fmt::DebugList, hash::Hash};
-
-use anyhow::Result;
-use aoc_2025::{input_path, input_path_test, lines, read_to_string};
-use minilp::{ComparisonOp, OptimizationDirection, Problem};
-use regex::Regex;
-use std::collections::VecDeque;
+use anyhow::{Context, Result};
+use aoc_2025::gf2::{BitVec, min_weight_solution};
+use aoc_2025::{input_path, lines, read_to_string};
+use itertools::Itertools;
+use serde_json::{Value, json};
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
 
 fn main() -> Result<()> {
     let input = read_to_string(input_path(10))?;
     let mut machines: Vec<Machine> = Vec::new();
-    for line in lines(&input) {
-        machines.push(Machine::new(line));
+    for (i, line) in input.lines().enumerate().filter(|(_, l)| !l.is_empty()) {
+        let machine = line
+            .parse()
+            .with_context(|| format!("invalid machine on line {}", i + 1))?;
+        machines.push(machine);
+    }
+
+    // `--check` makes sure every machine prints back as the line it came from
+    if std::env::args().any(|a| a == "--check") {
+        for (line, machine) in lines(&input).zip(&machines) {
+            let printed = machine.to_string();
+            anyhow::ensure!(
+                printed == line.trim(),
+                "{line:?} printed back as {printed:?}"
+            );
+        }
+        println!("Round-tripped {} machines", machines.len());
+        return Ok(());
+    }
+
+    // `--explain [machine]` replays the solution for one or every machine,
+    // as JSON with `--json`
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(pos) = args.iter().position(|a| a == "--explain") {
+        let only = match args.get(pos + 1).filter(|a| !a.starts_with("--")) {
+            Some(i) => Some(
+                i.parse::<usize>()
+                    .context("--explain takes a machine index")?,
+            ),
+            None => None,
+        };
+        let json = args.iter().any(|a| a == "--json");
+        return explain(&machines, only, json);
     }
+
     println!("Part 1: {}", part1(&machines)?);
     println!("Part 2: {}", part2(&machines)?);
     Ok(())
 }
 
-fn part1(machines: &Vec<Machine>) -> Result<u32> {
-    Ok(machines
+fn part1(machines: &[Machine]) -> Result<usize> {
+    machines
+        .iter()
+        .enumerate()
+        .map(|(i, m)| {
+            let presses = m
+                .min_presses()
+                .with_context(|| format!("machine {i}"))?
+                .with_context(|| format!("machine {i} can't reach its light pattern"))?;
+            Ok(presses.len())
+        })
+        .sum()
+}
+
+fn part2(machines: &[Machine]) -> Result<u32> {
+    machines
         .iter()
-        .map(|m| m.min_presses().expect("target state must be reachable"))
-        .sum())
+        .enumerate()
+        .map(|(i, m)| {
+            m.min_joltage_presses().map_err(|e| {
+                let reason = match e {
+                    Unsolved::Infeasible(_) => {
+                        format!("machine {i} can't reach its joltage targets")
+                    }
+                    Unsolved::GaveUp { .. } => format!("machine {i}'s joltage search gave up"),
+                };
+                anyhow::Error::new(e).context(reason)
+            })
+        })
+        .sum()
 }
 
-fn part2(machines: &Vec<Machine>) -> Result<u32> {
-    Ok(machines.iter().map(|m| m.min_joltage_presses()).sum())
+fn explain(machines: &[Machine], only: Option<usize>, json: bool) -> Result<()> {
+    let selected: Vec<usize> = match only {
+        Some(i) if i >= machines.len() => {
+            anyhow::bail!("there are only {} machines", machines.len())
+        }
+        Some(i) => vec![i],
+        None => (0..machines.len()).collect(),
+    };
+
+    let mut out = std::io::stdout().lock();
+    let mut reports = Vec::new();
+    for i in selected {
+        let explanation = machines[i]
+            .explain(i)
+            .with_context(|| format!("machine {i}"))?;
+        if json {
+            reports.push(explanation.to_json());
+        } else {
+            explanation.write_text(&machines[i], &mut out)?;
+        }
+    }
+    if json {
+        let value = if only.is_some() {
+            reports.remove(0)
+        } else {
+            Value::Array(reports)
+        };
+        serde_json::to_writer_pretty(&mut out, &value)?;
+        writeln!(out)?;
+    }
+    Ok(())
 }
 
 struct Machine {
@@ -33,170 +120,814 @@ struct Machine {
     desired_indicator_lights: Vec<IndicatorLight>,
     buttons: Vec<Button>,
     joltage_requirements: Vec<u32>,
+    /// Whether the start values were written out, even if all zero, so the
+    /// machine prints back as it was given.
+    start_given: bool,
     desired_joltage_requirements: Vec<u32>,
 }
 
-impl Machine {
-    fn new(input: &str) -> Self {
-        let re_brackets = Regex::new(r"\[(.*?)\]").unwrap();
-        let re_parens = Regex::new(r"\((.*?)\)").unwrap();
-        let re_braces = Regex::new(r"\{(.*?)\}").unwrap();
-
-        let bracket = re_brackets.captures(input).unwrap()[1].to_string();
-
-        let indicator_lights: Vec<IndicatorLight> =
-            vec![IndicatorLight { enabled: false }; bracket.chars().count()];
-        let mut desired_indicator_lights = indicator_lights.clone();
-        for (i, c) in bracket.chars().enumerate() {
-            match c {
-                '.' => desired_indicator_lights[i].enabled = false,
-                '#' => desired_indicator_lights[i].enabled = true,
-                _ => println!("hmm"),
+impl FromStr for Machine {
+    type Err = ParseError;
+
+    /// Parses `[.##.] (3) (1,3) {3,5,4,7}`: the target light pattern, any
+    /// number of buttons listing the lights (and counters) they affect, and
+    /// the target joltage of every counter.
+    ///
+    /// A button written `-(1,3)` decrements its counters instead, and
+    /// `(1,3)<=4` may be pressed at most four times. Counters start at zero
+    /// unless the braces give start values first, as in `{1,0,0,0->3,5,4,7}`.
+    fn from_str(input: &str) -> Result<Self, ParseError> {
+        let mut cursor = Cursor { input, pos: 0 };
+
+        cursor.skip_spaces();
+        cursor.expect(b'[', "'['")?;
+        let mut desired_indicator_lights = Vec::new();
+        loop {
+            let enabled = match cursor.peek() {
+                Some(b'.') => false,
+                Some(b'#') => true,
+                Some(b']') => break,
+                _ => return Err(cursor.unexpected("'.', '#' or ']'")),
+            };
+            desired_indicator_lights.push(IndicatorLight { enabled });
+            cursor.pos += 1;
+        }
+        cursor.pos += 1;
+        let indicator_lights =
+            vec![IndicatorLight { enabled: false }; desired_indicator_lights.len()];
+
+        let mut buttons = Vec::new();
+        loop {
+            cursor.skip_spaces();
+            match cursor.peek() {
+                Some(b'(' | b'-') => {
+                    let decrement = cursor.peek() == Some(b'-');
+                    if decrement {
+                        cursor.pos += 1;
+                    }
+                    cursor.expect(b'(', "'('")?;
+                    let (light_idxs, _) = cursor.numbers(b")")?;
+                    let limit = if cursor.input[cursor.pos..].starts_with("<=") {
+                        cursor.pos += 2;
+                        cursor.skip_spaces();
+                        Some(cursor.number()?)
+                    } else {
+                        None
+                    };
+                    buttons.push(Button {
+                        light_idxs,
+                        decrement,
+                        limit,
+                    });
+                }
+                Some(b'{') => break,
+                _ => return Err(cursor.unexpected("'(' or '{'")),
             }
         }
 
-        let parens: Vec<String> = re_parens
-            .captures_iter(input)
-            .map(|c| c[1].to_string())
-            .collect();
-
-        let buttons: Vec<Button> = parens
-            .into_iter()
-            .map(|s| Button {
-                light_idxs: s
-                    .split(',')
-                    .map(|n| n.trim().parse::<u32>().unwrap())
-                    .collect(),
-            })
-            .collect();
+        cursor.expect(b'{', "'{'")?;
+        let (first, closed_by) = cursor.numbers(b"}-")?;
+        let start_given = closed_by == b'-';
+        let (joltage_requirements, desired_joltage_requirements) = if start_given {
+            cursor.pos -= 1;
+            cursor.expect(b'-', "'->'")?;
+            cursor.expect(b'>', "'->'")?;
+            let (targets, _) = cursor.numbers(b"}")?;
+            if targets.len() != first.len() {
+                return Err(ParseError::CounterCountMismatch {
+                    start: first.len(),
+                    target: targets.len(),
+                });
+            }
+            (first, targets)
+        } else {
+            (vec![0; first.len()], first)
+        };
+
+        cursor.skip_spaces();
+        if cursor.peek().is_some() {
+            return Err(cursor.unexpected("end of line"));
+        }
 
-        let braces = re_braces.captures(input).unwrap()[1].to_string();
-        let joltage_requirements: Vec<u32> = vec![0; braces.split(',').count()];
-        let desired_joltage_requirements = braces
-            .split(',')
-            .map(|n| n.parse::<u32>().unwrap())
-            .collect();
+        let lights = desired_indicator_lights.len();
+        let counters = desired_joltage_requirements.len();
+        for (button, b) in buttons.iter().enumerate() {
+            for (i, &index) in b.light_idxs.iter().enumerate() {
+                if b.light_idxs[..i].contains(&index) {
+                    return Err(ParseError::DuplicateIndex { button, index });
+                }
+                if index as usize >= lights {
+                    return Err(ParseError::LightOutOfRange {
+                        button,
+                        index,
+                        lights,
+                    });
+                }
+                if index as usize >= counters {
+                    return Err(ParseError::CounterOutOfRange {
+                        button,
+                        index,
+                        counters,
+                    });
+                }
+            }
+        }
 
-        Machine {
+        Ok(Machine {
             indicator_lights,
             desired_indicator_lights,
-            buttons: buttons,
-            joltage_requirements: joltage_requirements,
-            desired_joltage_requirements: desired_joltage_requirements,
+            buttons,
+            joltage_requirements,
+            start_given,
+            desired_joltage_requirements,
+        })
+    }
+}
+
+impl fmt::Display for Machine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[")?;
+        for light in &self.desired_indicator_lights {
+            write!(f, "{}", if light.enabled { '#' } else { '.' })?;
+        }
+        write!(f, "]")?;
+        for button in &self.buttons {
+            write!(f, " {button}")?;
         }
+        write!(f, " {{")?;
+        if self.start_given {
+            write!(f, "{}->", self.joltage_requirements.iter().join(","))?;
+        }
+        write!(
+            f,
+            "{}}}",
+            self.desired_joltage_requirements.iter().join(",")
+        )
     }
+}
 
-    fn target_mask(&self) -> u64 {
-        let mut mask = 0u64;
-        for (i, light) in self.desired_indicator_lights.iter().enumerate() {
-            if light.enabled {
-                mask |= 1u64 << i;
-            }
+impl fmt::Display for Button {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.decrement {
+            write!(f, "-")?;
         }
-        mask
+        write!(f, "({})", self.light_idxs.iter().join(","))?;
+        if let Some(limit) = self.limit {
+            write!(f, "<={limit}")?;
+        }
+        Ok(())
     }
+}
 
-    fn button_masks(&self) -> Vec<u64> {
-        let mut res = Vec::with_capacity(self.buttons.len());
-        for button in &self.buttons {
-            let mut mask = 0u64;
-            for &idx in &button.light_idxs {
-                mask |= 1u64 << (idx as u64);
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ParseError {
+    /// Byte offset, what was there (`None` at the end of the line) and what
+    /// should have been.
+    Unexpected {
+        pos: usize,
+        found: Option<char>,
+        expected: &'static str,
+    },
+    InvalidNumber {
+        pos: usize,
+    },
+    LightOutOfRange {
+        button: usize,
+        index: u32,
+        lights: usize,
+    },
+    CounterOutOfRange {
+        button: usize,
+        index: u32,
+        counters: usize,
+    },
+    /// A button listing the same index twice.
+    DuplicateIndex {
+        button: usize,
+        index: u32,
+    },
+    CounterCountMismatch {
+        start: usize,
+        target: usize,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Unexpected {
+                pos,
+                found: Some(c),
+                expected,
+            } => write!(f, "expected {expected} at column {}, found {c:?}", pos + 1),
+            ParseError::Unexpected {
+                pos,
+                found: None,
+                expected,
+            } => write!(
+                f,
+                "expected {expected} at column {}, found end of line",
+                pos + 1
+            ),
+            ParseError::InvalidNumber { pos } => {
+                write!(f, "invalid number at column {}", pos + 1)
             }
-            res.push(mask);
+            ParseError::LightOutOfRange {
+                button,
+                index,
+                lights,
+            } => write!(
+                f,
+                "button {button} toggles light {index}, but there are only {lights} lights"
+            ),
+            ParseError::CounterOutOfRange {
+                button,
+                index,
+                counters,
+            } => write!(
+                f,
+                "button {button} changes counter {index}, but there are only {counters} counters"
+            ),
+            ParseError::DuplicateIndex { button, index } => {
+                write!(f, "button {button} lists index {index} more than once")
+            }
+            ParseError::CounterCountMismatch { start, target } => write!(
+                f,
+                "{start} start values given for {target} joltage counters"
+            ),
         }
-        res
     }
+}
 
-    fn min_presses(&self) -> Option<u32> {
-        let n = self.desired_indicator_lights.len();
+impl std::error::Error for ParseError {}
 
-        if n > 64 {
-            panic!("Too many indicator lights ({n}), bitmask won't fit in u64");
-        }
+struct Cursor<'a> {
+    input: &'a str,
+    pos: usize,
+}
 
-        let target = self.target_mask();
-        if target == 0 {
-            return Some(0);
-        }
+impl Cursor<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.pos).copied()
+    }
 
-        let button_masks = self.button_masks();
+    fn skip_spaces(&mut self) {
+        while self.peek().is_some_and(|b| b.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
 
-        let state_count = 1usize << n;
-        let mut dist = vec![u32::MAX; state_count];
+    fn expect(&mut self, byte: u8, expected: &'static str) -> Result<(), ParseError> {
+        self.skip_spaces();
+        match self.peek() {
+            Some(b) if b == byte => {
+                self.pos += 1;
+                Ok(())
+            }
+            _ => Err(self.unexpected(expected)),
+        }
+    }
 
-        let mut queue = VecDeque::new();
-        dist[0] = 0;
-        queue.push_back(0u64);
+    /// An error for whatever character is at the current position.
+    fn unexpected(&self, expected: &'static str) -> ParseError {
+        ParseError::Unexpected {
+            pos: self.pos,
+            found: self.input[self.pos..].chars().next(),
+            expected,
+        }
+    }
 
-        while let Some(state) = queue.pop_front() {
-            let d = dist[state as usize];
+    fn number(&mut self) -> Result<u32, ParseError> {
+        let start = self.pos;
+        while self.peek().is_some_and(|b| b.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(self.unexpected("a number"));
+        }
+        self.input[start..self.pos]
+            .parse()
+            .map_err(|_| ParseError::InvalidNumber { pos: start })
+    }
 
-            for &bmask in &button_masks {
-                let next = state ^ bmask;
-                let idx = next as usize;
-                if dist[idx] == u32::MAX {
-                    dist[idx] = d + 1;
-                    if next == target {
-                        return Some(d + 1);
-                    }
-                    queue.push_back(next);
+    /// A non-empty comma-separated list of numbers ended by one of `close`,
+    /// which is returned alongside.
+    fn numbers(&mut self, close: &[u8]) -> Result<(Vec<u32>, u8), ParseError> {
+        let mut numbers = Vec::new();
+        loop {
+            self.skip_spaces();
+            numbers.push(self.number()?);
+
+            self.skip_spaces();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b) if close.contains(&b) => {
+                    self.pos += 1;
+                    return Ok((numbers, b));
                 }
+                _ => return Err(self.unexpected("',' or a closing bracket")),
             }
         }
+    }
+}
 
-        None
+impl Machine {
+    /// Lights whose state differs from the target, i.e. the ones an odd
+    /// number of presses has to toggle.
+    fn target_lights(&self) -> BitVec {
+        let lights = self
+            .indicator_lights
+            .iter()
+            .zip(&self.desired_indicator_lights);
+        let wrong = lights
+            .enumerate()
+            .filter(|(_, (start, want))| start.enabled != want.enabled);
+        BitVec::from_indices(self.indicator_lights.len(), wrong.map(|(i, _)| i))
     }
 
-    fn min_joltage_presses(&self) -> u32 {
-        let buttons: Vec<Vec<usize>> = self
-            .buttons
+    /// The lights each button toggles; decrementing toggles just the same,
+    /// and a button that may not be pressed at all toggles nothing.
+    fn button_lights(&self) -> Vec<BitVec> {
+        let n = self.indicator_lights.len();
+        self.buttons
             .iter()
-            .map(|b| b.light_idxs.iter().map(|&i| i as usize).collect())
-            .collect();
+            .map(|b| {
+                let lights = b.light_idxs.iter().map(|&i| i as usize);
+                BitVec::from_indices(n, lights.filter(|_| b.limit != Some(0)))
+            })
+            .collect()
+    }
+
+    /// Buttons to press once each (pressing twice cancels out) to light the
+    /// target pattern with as few presses as possible, or `None` if it can't
+    /// be reached.
+    fn min_presses(&self) -> Result<Option<Vec<usize>>> {
+        let solution = min_weight_solution(&self.button_lights(), &self.target_lights())?;
+        Ok(solution.map(|presses| presses.ones().collect()))
+    }
 
-        let delta: Vec<i64> = self
+    /// Fewest total presses that bring every counter from its start to its
+    /// target.
+    fn min_joltage_presses(&self) -> Result<u32, Unsolved> {
+        self.joltage_presses().map(|presses| presses.iter().sum())
+    }
+
+    /// How often to press each button for the fewest total presses.
+    fn joltage_presses(&self) -> Result<Vec<u32>, Unsolved> {
+        let counters = self.desired_joltage_requirements.len();
+
+        let delta: Vec<i128> = self
             .desired_joltage_requirements
             .iter()
             .zip(self.joltage_requirements.iter())
-            .map(|(d, s)| *d as i64 - *s as i64)
+            .map(|(d, s)| *d as i128 - *s as i128)
             .collect();
 
-        if delta.iter().any(|&x| x < 0) {
-            panic!("Target joltage state is unreachable (target < start for some light)");
+        let mut matrix = vec![vec![0i128; self.buttons.len()]; counters];
+        for (button_idx, button) in self.buttons.iter().enumerate() {
+            for &counter in &button.light_idxs {
+                matrix[counter as usize][button_idx] = button.step();
+            }
         }
 
-        let jolts: Vec<f64> = delta.iter().map(|&x| x as f64).collect();
+        // a counter that only ever moves one way can't be overshot, so it caps
+        // every button that moves it
+        let bounds: Vec<Option<i128>> = self
+            .buttons
+            .iter()
+            .map(|b| {
+                let monotone = b.light_idxs.iter().map(|&c| &matrix[c as usize]);
+                let derived = monotone
+                    .zip(b.light_idxs.iter())
+                    .filter(|(row, _)| row.iter().all(|&v| v == 0 || v == b.step()))
+                    .map(|(_, &c)| delta[c as usize] * b.step())
+                    .min();
+                let limit = b.limit.map(i128::from);
+                derived.into_iter().chain(limit).min()
+            })
+            .collect();
 
-        let mut problem = Problem::new(OptimizationDirection::Minimize);
+        let to_presses = |p: Vec<i128>| p.into_iter().map(|p| p as u32).collect();
+
+        // with every button bounded, or no free choice left at all, one
+        // search settles it
+        let uncapped = bounds.iter().map(|b| b.unwrap_or(i128::MAX)).collect();
+        let solver = PressSolver::new(matrix.clone(), delta.clone(), uncapped)
+            .ok_or(Infeasible::Inconsistent)?;
+        if solver.free.is_empty() || bounds.iter().all(Option::is_some) {
+            return solver
+                .minimise()
+                .map(to_presses)
+                .ok_or(Infeasible::NoSolution.into());
+        }
 
-        let press_vars: Vec<_> = (0..buttons.len())
-            .map(|_| problem.add_var(1.0, (0.0, f64::INFINITY)))
+        // Unbounded buttons get a cap that doubles until some solution turns
+        // up. Once one with `total` presses is known, a better one can't press
+        // any button more than `total` times, so a search with that cap is
+        // conclusive.
+        let search = |cap: i128| -> Result<Option<Vec<i128>>, Unsolved> {
+            let capped = bounds.iter().map(|b| b.unwrap_or(cap)).collect();
+            let solver = PressSolver::new(matrix.clone(), delta.clone(), capped)
+                .ok_or(Infeasible::Inconsistent)?;
+            Ok(solver.minimise())
+        };
+        let mut cap = delta.iter().map(|d| d.abs()).max().unwrap_or(0).max(1);
+        loop {
+            if let Some(presses) = search(cap)? {
+                let total = presses.iter().sum::<i128>();
+                if total <= cap {
+                    return Ok(to_presses(presses));
+                }
+                let best = search(total)?.unwrap_or(presses);
+                return Ok(to_presses(best));
+            }
+            if cap >= MAX_PRESS_CAP {
+                return Err(Unsolved::GaveUp { cap });
+            }
+            cap *= 2;
+        }
+    }
+
+    /// Solves both parts for this machine and replays the presses.
+    fn explain(&self, index: usize) -> Result<Explanation> {
+        let lights = self.min_presses()?.map(|buttons| {
+            let start = State::Lights(self.indicator_lights.iter().map(|l| l.enabled).collect());
+            let target = State::Lights(
+                self.desired_indicator_lights
+                    .iter()
+                    .map(|l| l.enabled)
+                    .collect(),
+            );
+            self.trace(
+                buttons.into_iter().map(|b| (b, 1)).collect(),
+                start,
+                &target,
+            )
+        });
+
+        let joltage = self.joltage_presses().map(|counts| {
+            let presses = counts.into_iter().enumerate().filter(|&(_, n)| n > 0);
+            let counters = |c: &[u32]| State::Counters(c.iter().map(|&v| v as i64).collect());
+            let start = counters(&self.joltage_requirements);
+            let target = counters(&self.desired_joltage_requirements);
+            self.trace(presses.collect(), start, &target)
+        });
+
+        Ok(Explanation {
+            machine: index,
+            description: self.to_string(),
+            lights,
+            joltage,
+        })
+    }
+
+    /// Applies `presses` one at a time from `state`, recording each step.
+    fn trace(&self, presses: Vec<(usize, u32)>, mut state: State, target: &State) -> Trace {
+        let mut steps = Vec::new();
+        for &(button, times) in &presses {
+            for _ in 0..times {
+                state.press(&self.buttons[button]);
+                steps.push((button, state.clone()));
+            }
+        }
+        Trace {
+            matches_target: state == *target,
+            presses,
+            steps,
+        }
+    }
+}
+
+/// Largest cap tried for buttons without any bound on their presses before
+/// the search gives up without having found a solution.
+const MAX_PRESS_CAP: i128 = 1 << 12;
+
+/// Why no press counts reach the joltage targets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Infeasible {
+    /// The counter changes aren't a combination of the buttons at all.
+    Inconsistent,
+    /// No whole, non-negative press counts within the limits work.
+    NoSolution,
+}
+
+impl fmt::Display for Infeasible {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Infeasible::Inconsistent => write!(f, "no combination of buttons gives those changes"),
+            Infeasible::NoSolution => write!(f, "no whole press counts within the limits work"),
+        }
+    }
+}
+
+impl std::error::Error for Infeasible {}
+
+/// Why the joltage search returned no press counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Unsolved {
+    /// The targets are provably out of reach.
+    Infeasible(Infeasible),
+    /// Unknown either way: nothing turned up with every unlimited button
+    /// pressed at most `cap` times.
+    GaveUp { cap: i128 },
+}
+
+impl From<Infeasible> for Unsolved {
+    fn from(reason: Infeasible) -> Self {
+        Unsolved::Infeasible(reason)
+    }
+}
+
+impl fmt::Display for Unsolved {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Unsolved::Infeasible(reason) => write!(f, "{reason}"),
+            Unsolved::GaveUp { cap } => write!(
+                f,
+                "unknown: no solution with unlimited buttons pressed up to {cap} times"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Unsolved {}
+
+/// How one machine reaches its targets; the lights are `None` when their
+/// pattern is unreachable.
+struct Explanation {
+    machine: usize,
+    description: String,
+    lights: Option<Trace>,
+    joltage: Result<Trace, Unsolved>,
+}
+
+struct Trace {
+    /// `(button, times)` for every button that gets pressed.
+    presses: Vec<(usize, u32)>,
+    /// Every single press in order, with the state right after it.
+    steps: Vec<(usize, State)>,
+    /// Checked independently of the solver by replaying the presses.
+    matches_target: bool,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+enum State {
+    Lights(Vec<bool>),
+    Counters(Vec<i64>),
+}
+
+impl State {
+    fn press(&mut self, button: &Button) {
+        for &i in &button.light_idxs {
+            match self {
+                State::Lights(lights) => lights[i as usize] ^= true,
+                State::Counters(counters) => counters[i as usize] += button.step() as i64,
+            }
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        match self {
+            State::Lights(_) => json!(self.to_string()),
+            State::Counters(counters) => json!(counters),
+        }
+    }
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            State::Lights(lights) => {
+                let pattern: String = lights
+                    .iter()
+                    .map(|&on| if on { '#' } else { '.' })
+                    .collect();
+                write!(f, "[{pattern}]")
+            }
+            State::Counters(counters) => write!(f, "{{{}}}", counters.iter().join(",")),
+        }
+    }
+}
+
+impl Explanation {
+    fn write_text(&self, machine: &Machine, out: &mut impl Write) -> std::io::Result<()> {
+        writeln!(out, "Machine {}: {}", self.machine, self.description)?;
+        let lights = self.lights.as_ref().ok_or("unreachable".to_string());
+        let joltage = self.joltage.as_ref().map_err(|e| e.to_string());
+        for (name, trace) in [("Lights", lights), ("Joltage", joltage)] {
+            let trace = match trace {
+                Ok(trace) => trace,
+                Err(reason) => {
+                    writeln!(out, "  {name}: {reason}")?;
+                    continue;
+                }
+            };
+            let button = |b: usize| machine.buttons[b].to_string();
+            let presses = trace
+                .presses
+                .iter()
+                .map(|&(b, times)| format!("{} x{times}", button(b)))
+                .join(", ");
+            write!(out, "  {name} ({} presses)", trace.steps.len())?;
+            if presses.is_empty() {
+                writeln!(out)?;
+            } else {
+                writeln!(out, ": {presses}")?;
+            }
+            for (b, state) in &trace.steps {
+                writeln!(out, "    press {} -> {state}", button(*b))?;
+            }
+            let verdict = if trace.matches_target { "yes" } else { "NO" };
+            writeln!(out, "    matches target: {verdict}")?;
+        }
+        Ok(())
+    }
+
+    fn to_json(&self) -> Value {
+        let trace = |t: &Trace| {
+            json!({
+                "total": t.steps.len(),
+                "presses": t.presses.iter().map(|&(button, times)| {
+                    json!({ "button": button, "times": times })
+                }).collect::<Vec<_>>(),
+                "steps": t.steps.iter().map(|(button, state)| {
+                    json!({ "button": button, "state": state.to_json() })
+                }).collect::<Vec<_>>(),
+                "matches_target": t.matches_target,
+            })
+        };
+        let joltage = match &self.joltage {
+            Ok(t) => trace(t),
+            Err(reason @ Unsolved::Infeasible(_)) => json!({ "infeasible": reason.to_string() }),
+            Err(Unsolved::GaveUp { cap }) => json!({ "unknown": { "cap": cap } }),
+        };
+        json!({
+            "machine": self.machine,
+            "description": self.description,
+            "lights": self.lights.as_ref().map(trace),
+            "joltage": joltage,
+        })
+    }
+}
+
+/// Exact integer solver for `A x = b` with `0 <= x <= bounds`, minimising
+/// the sum of `x`.
+///
+/// Gauss-Jordan elimination over the rationals (kept fraction-free in `i128`)
+/// expresses every pivot variable in terms of the free ones. Every assignment
+/// of the free variables within their bounds is then tried, so the minimum
+/// found is proven optimal.
+struct PressSolver {
+    /// Reduced rows: one coefficient per variable, then the right-hand side.
+    rows: Vec<Vec<i128>>,
+    /// The variable each row solves for; its coefficient is positive.
+    pivots: Vec<usize>,
+    free: Vec<usize>,
+    bounds: Vec<i128>,
+    /// Position in `free` after which each row's pivot is fully determined.
+    ready_at: Vec<Option<usize>>,
+}
+
+impl PressSolver {
+    /// Returns `None` when the system has no rational solution at all.
+    fn new(matrix: Vec<Vec<i128>>, rhs: Vec<i128>, bounds: Vec<i128>) -> Option<Self> {
+        let vars = bounds.len();
+        let mut rows: Vec<Vec<i128>> = matrix
+            .into_iter()
+            .zip(rhs)
+            .map(|(mut row, b)| {
+                row.push(b);
+                row
+            })
             .collect();
 
-        for (light_idx, &required) in jolts.iter().enumerate() {
-            if required == 0.0 {
+        let mut pivots = Vec::new();
+        for col in 0..vars {
+            let r = pivots.len();
+            let Some(k) = (r..rows.len()).find(|&k| rows[k][col] != 0) else {
                 continue;
-            }
+            };
+            rows.swap(r, k);
 
-            let mut terms = Vec::new();
-            for (button_idx, button) in buttons.iter().enumerate() {
-                if button.contains(&light_idx) {
-                    terms.push((press_vars[button_idx], 1.0));
+            for k in 0..rows.len() {
+                if k == r || rows[k][col] == 0 {
+                    continue;
+                }
+                let (p, f) = (rows[r][col], rows[k][col]);
+                let pivot_row = rows[r].clone();
+                for (v, pv) in rows[k].iter_mut().zip(pivot_row) {
+                    *v = *v * p - pv * f;
                 }
+                normalise(&mut rows[k]);
+            }
+            pivots.push(col);
+        }
+
+        // leftover rows read `0 = rhs`
+        if rows[pivots.len()..].iter().any(|row| row[vars] != 0) {
+            return None;
+        }
+        rows.truncate(pivots.len());
+        for (row, &col) in rows.iter_mut().zip(&pivots) {
+            if row[col] < 0 {
+                row.iter_mut().for_each(|v| *v = -*v);
+            }
+        }
+
+        let mut free: Vec<usize> = (0..vars).filter(|v| !pivots.contains(v)).collect();
+        free.sort_by_key(|&v| bounds[v]);
+        let ready_at = rows
+            .iter()
+            .map(|row| free.iter().rposition(|&f| row[f] != 0))
+            .collect();
+
+        Some(PressSolver {
+            rows,
+            pivots,
+            free,
+            bounds,
+            ready_at,
+        })
+    }
+
+    fn minimise(&self) -> Option<Vec<i128>> {
+        let mut values = vec![0i128; self.bounds.len()];
+        let mut best: Option<(i128, Vec<i128>)> = None;
+
+        // rows without free variables are fixed from the start
+        if self.settle(None, &mut values) {
+            self.search(0, 0, &mut values, &mut best);
+        }
+
+        best.map(|(_, presses)| presses)
+    }
+
+    fn search(
+        &self,
+        pos: usize,
+        free_sum: i128,
+        values: &mut Vec<i128>,
+        best: &mut Option<(i128, Vec<i128>)>,
+    ) {
+        // pivots never go negative, so the free presses alone bound the total
+        if best.as_ref().is_some_and(|(b, _)| free_sum >= *b) {
+            return;
+        }
+
+        if pos == self.free.len() {
+            let total: i128 = values.iter().sum();
+            if best.as_ref().is_none_or(|(b, _)| total < *b) {
+                *best = Some((total, values.clone()));
             }
+            return;
+        }
 
-            problem.add_constraint(&terms, ComparisonOp::Eq, required);
+        let var = self.free[pos];
+        for v in 0..=self.bounds[var] {
+            values[var] = v;
+            if self.settle(Some(pos), values) {
+                self.search(pos + 1, free_sum + v, values, best);
+            }
         }
+        values[var] = 0;
+    }
 
-        let solution = problem
-            .solve()
-            .expect("LP solver could not find a feasible solution");
+    /// Solves the pivots of rows that become determined at `pos`, returning
+    /// `false` if one isn't a whole number within its bounds.
+    fn settle(&self, pos: Option<usize>, values: &mut [i128]) -> bool {
+        let vars = self.bounds.len();
+        for (r, row) in self.rows.iter().enumerate() {
+            if self.ready_at[r] != pos {
+                continue;
+            }
+            let rest: i128 = self.free.iter().map(|&f| row[f] * values[f]).sum();
+            let numerator = row[vars] - rest;
+            let pivot = self.pivots[r];
+            if numerator % row[pivot] != 0 {
+                return false;
+            }
+            let value = numerator / row[pivot];
+            if value < 0 || value > self.bounds[pivot] {
+                return false;
+            }
+            values[pivot] = value;
+        }
+        true
+    }
+}
 
-        press_vars.iter().map(|&v| solution[v].round() as u32).sum()
+/// Divides a row by the gcd of its entries.
+fn normalise(row: &mut [i128]) {
+    let g = row.iter().fold(0i128, |g, &v| gcd(g, v.abs()));
+    if g > 1 {
+        row.iter_mut().for_each(|v| *v /= g);
     }
 }
 
+fn gcd(a: i128, b: i128) -> i128 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
 #[derive(Clone)]
 struct IndicatorLight {
     enabled: bool,
@@ -205,4 +936,13 @@ struct IndicatorLight {
 #[derive(Clone)]
 struct Button {
     light_idxs: Vec<u32>,
+    decrement: bool,
+    limit: Option<u32>,
+}
+
+impl Button {
+    /// How much one press changes each of its counters.
+    fn step(&self) -> i128 {
+        if self.decrement { -1 } else { 1 }
+    }
 }
@@ -1,123 +1,921 @@
-use std::{
-    collections::{HashMap, HashSet},
-    fmt::DebugList,
-};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
 
-use anyhow::Result;
-use aoc_2025::{input_path, input_path_test, lines, read_to_string};
+use anyhow::{Context, Result, bail};
+use aoc_2025::counting::{Checked, Counter, Exact, Modular, Overflow};
+use aoc_2025::rng::XorShift;
+use aoc_2025::{input_path, read_to_string};
+use itertools::Itertools;
 
 fn main() -> Result<()> {
     let input = read_to_string(input_path(11))?;
-    let devices = create_devices(&input);
-    println!("Part 1: {}", part1(&devices)?);
-    println!("Part 2: {}", part2(&devices)?);
+    let graph = DeviceGraph::parse(&input)?;
+
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(pos) = args.iter().position(|a| a == "--paths") {
+        return list_paths(&graph, &args, pos);
+    }
+
+    // `--dot <file>` draws the network from `you`/`svr` to `out` for Graphviz
+    if let Some(pos) = args.iter().position(|a| a == "--dot") {
+        let path = args.get(pos + 1).context("--dot needs a file path")?;
+        let existing = |names: &[&str]| names.iter().filter_map(|n| graph.id(n)).collect();
+        let sources: Vec<u32> = existing(&["you", "svr"]);
+        let waypoints: Vec<u32> = existing(&["dac", "fft"]);
+        let sink = graph.require("out")?;
+        let cycles = graph.write_dot(
+            BufWriter::new(File::create(path)?),
+            &sources,
+            sink,
+            &waypoints,
+        )?;
+        println!("Wrote {} devices to {path}", graph.len());
+        for cycle in cycles {
+            eprintln!("warning: {cycle}");
+        }
+        return Ok(());
+    }
+
+    // counts are `u64` and fail on overflow, unless `--exact` asks for big
+    // integers or `--modulo <p>` reduces them modulo a prime
+    if args.iter().any(|a| a == "--exact") {
+        return report(&graph, &args, &Exact);
+    }
+    if let Some(pos) = args.iter().position(|a| a == "--modulo") {
+        let p: u64 = args
+            .get(pos + 1)
+            .and_then(|p| p.parse().ok())
+            .context("--modulo takes a prime")?;
+        let counter = Modular::new(p).with_context(|| format!("--modulo {p} is not prime"))?;
+        return report(&graph, &args, &counter);
+    }
+    report(&graph, &args, &Checked::<u64>::new())
+}
+
+/// Prints both parts, or the answer to a `--count` query, counted with
+/// `counter`.
+fn report<C: Counter>(graph: &DeviceGraph, args: &[String], counter: &C) -> Result<()>
+where
+    C::Value: fmt::Display,
+{
+    if let Some(pos) = args.iter().position(|a| a == "--count") {
+        println!("{}", query(graph, args, pos)?.count_with(counter)?);
+        return Ok(());
+    }
+    println!("Part 1: {}", part1(graph, counter)?);
+    println!("Part 2: {}", part2(graph, counter)?);
     Ok(())
 }
 
-fn create_devices(input: &str) -> HashMap<String, Vec<String>> {
-    let mut devices = HashMap::new();
+fn part1<C: Counter>(graph: &DeviceGraph, counter: &C) -> Result<C::Value> {
+    graph
+        .paths(graph.require("you")?, graph.require("out")?)
+        .count_with(counter)
+}
+
+fn part2<C: Counter>(graph: &DeviceGraph, counter: &C) -> Result<C::Value> {
+    graph
+        .paths(graph.require("svr")?, graph.require("out")?)
+        .through(graph.require("dac")?)
+        .through(graph.require("fft")?)
+        .count_with(counter)
+}
 
-    for raw in lines(input) {
-        let line = raw.trim();
-        if line.is_empty() {
-            continue;
+/// Builds the query for `--count <from> <to>` or `--paths <from> <to>` with
+/// optional `--via a,b`, `--avoid c,d` and `--order e,f` constraints.
+fn query<'a>(graph: &'a DeviceGraph, args: &[String], pos: usize) -> Result<PathQuery<'a>> {
+    let device = |i: usize| -> Result<u32> {
+        let name = args
+            .get(i)
+            .with_context(|| format!("{} takes a start and an end device", args[pos]))?;
+        graph.require(name)
+    };
+    let list = |flag: &str| -> Result<Vec<u32>> {
+        match args.iter().position(|a| a == flag) {
+            Some(i) => {
+                let names = args
+                    .get(i + 1)
+                    .with_context(|| format!("{flag} takes a comma-separated list of devices"))?;
+                names.split(',').map(|n| graph.require(n.trim())).collect()
+            }
+            None => Ok(Vec::new()),
         }
+    };
 
-        let (left, right) = line.split_once(':').unwrap();
-        let name = left.trim().to_string();
+    let mut query = graph.paths(device(pos + 1)?, device(pos + 2)?);
+    for node in list("--via")? {
+        query = query.through(node);
+    }
+    for node in list("--avoid")? {
+        query = query.avoiding(node);
+    }
+    let order = list("--order")?;
+    if !order.is_empty() {
+        query = query.in_order(&order);
+    }
+    Ok(query)
+}
 
-        let outputs = right
-            .split_whitespace()
-            .map(|s| s.trim().to_string())
-            .collect::<Vec<_>>();
+/// Answers `--paths`: the first `--first n` paths (10 by default) in
+/// lexicographic order, the `--nth k` path, or `--sample n` uniformly random
+/// ones (seeded by `--seed`).
+fn list_paths(graph: &DeviceGraph, args: &[String], pos: usize) -> Result<()> {
+    let number = |flag: &str| -> Result<Option<u64>> {
+        match args.iter().position(|a| a == flag) {
+            Some(i) => {
+                let n = args
+                    .get(i + 1)
+                    .with_context(|| format!("{flag} takes a number"))?;
+                Ok(Some(
+                    n.parse()
+                        .with_context(|| format!("{flag} takes a number"))?,
+                ))
+            }
+            None => Ok(None),
+        }
+    };
+
+    let table = query(graph, args, pos)?.table()?;
+    let total = table.total;
+    let paths: Vec<Vec<u32>> = if let Some(k) = number("--nth")? {
+        table.nth(graph, k).into_iter().collect()
+    } else if let Some(n) = number("--sample")? {
+        let mut rng = XorShift::new(number("--seed")?.unwrap_or(0x2545_f491_4f6c_dd1d));
+        match total {
+            0 => Vec::new(),
+            _ => (0..n)
+                .filter_map(|_| table.nth(graph, rng.below(total)))
+                .collect(),
+        }
+    } else {
+        let n = number("--first")?.unwrap_or(10).min(total);
+        (0..n).filter_map(|k| table.nth(graph, k)).collect()
+    };
 
-        devices.insert(name, outputs);
+    println!("{total} paths");
+    for path in paths {
+        println!("{}", path.iter().map(|&v| graph.name(v)).join(" -> "));
     }
+    Ok(())
+}
 
-    devices
+/// Devices and their output cables, with names interned to dense `u32` ids.
+/// Both edge directions are stored in compressed sparse row form.
+struct DeviceGraph {
+    names: Vec<String>,
+    ids: HashMap<String, u32>,
+    offsets: Vec<usize>,
+    targets: Vec<u32>,
+    rev_offsets: Vec<usize>,
+    rev_targets: Vec<u32>,
 }
 
-fn part1(input: &HashMap<String, Vec<String>>) -> Result<u64> {
-    let start = "you";
-    let end = "out";
+impl DeviceGraph {
+    /// Parses `name: output output ...` lines. Devices that only appear as
+    /// outputs get a node without outgoing edges.
+    fn parse(input: &str) -> Result<Self> {
+        let mut names: Vec<String> = Vec::new();
+        let mut ids: HashMap<String, u32> = HashMap::new();
+        let mut intern = |name: &str| -> u32 {
+            *ids.entry(name.to_string()).or_insert_with(|| {
+                names.push(name.to_string());
+                (names.len() - 1) as u32
+            })
+        };
 
-    let mut visited = HashSet::new();
-    let paths = dfs_count(start, end, input, &mut visited);
+        let mut edges: Vec<(u32, u32)> = Vec::new();
+        let mut defined: HashMap<u32, usize> = HashMap::new();
+        for (i, raw) in input.lines().enumerate() {
+            let line = raw.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (left, right) = line
+                .split_once(':')
+                .with_context(|| format!("line {} has no ':': {line:?}", i + 1))?;
+            let name = left.trim();
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                bail!("line {} has an invalid device name {name:?}", i + 1);
+            }
 
-    Ok(paths)
-}
+            let from = intern(name);
+            if let Some(first) = defined.insert(from, i + 1) {
+                bail!("device {name} is defined on lines {first} and {}", i + 1);
+            }
+            for output in right.split_whitespace() {
+                edges.push((from, intern(output)));
+            }
+        }
+
+        let n = names.len();
+        let (offsets, targets) = csr(n, edges.iter().copied());
+        let (rev_offsets, rev_targets) = csr(n, edges.iter().map(|&(a, b)| (b, a)));
+        Ok(DeviceGraph {
+            names,
+            ids,
+            offsets,
+            targets,
+            rev_offsets,
+            rev_targets,
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    fn id(&self, name: &str) -> Option<u32> {
+        self.ids.get(name).copied()
+    }
+
+    /// Like [`id`](Self::id), but a missing device is an error.
+    fn require(&self, name: &str) -> Result<u32> {
+        self.id(name)
+            .with_context(|| format!("no device named {name}"))
+    }
+
+    fn name(&self, id: u32) -> &str {
+        &self.names[id as usize]
+    }
+
+    fn successors(&self, id: u32) -> &[u32] {
+        &self.targets[self.offsets[id as usize]..self.offsets[id as usize + 1]]
+    }
+
+    fn predecessors(&self, id: u32) -> &[u32] {
+        &self.rev_targets[self.rev_offsets[id as usize]..self.rev_offsets[id as usize + 1]]
+    }
+
+    /// Every `allowed` node reachable from `start` along edges (or against
+    /// them when `reverse` is set) without leaving `allowed`, `start` included.
+    fn reachable(&self, start: u32, reverse: bool, allowed: &[bool]) -> Vec<bool> {
+        let mut seen = vec![false; self.len()];
+        let mut queue = VecDeque::from([start]);
+        seen[start as usize] = true;
+        while let Some(v) = queue.pop_front() {
+            let next = if reverse {
+                self.predecessors(v)
+            } else {
+                self.successors(v)
+            };
+            for &w in next {
+                if allowed[w as usize] && !seen[w as usize] {
+                    seen[w as usize] = true;
+                    queue.push_back(w);
+                }
+            }
+        }
+        seen
+    }
+
+    /// Strongly connected components of the nodes in `keep`, found with an
+    /// iterative Tarjan. Components come out in reverse topological order:
+    /// nothing in a component points into a later one.
+    fn strongly_connected_components(&self, keep: &[bool]) -> Vec<Vec<u32>> {
+        let mut tarjan = Tarjan::new(self.len());
+        for root in (0..self.len() as u32).filter(|&v| keep[v as usize]) {
+            if tarjan.index[root as usize] != Tarjan::UNVISITED {
+                continue;
+            }
+            tarjan.enter(root);
+
+            while let Some(&(v, edge)) = tarjan.calls.last() {
+                if let Some(&w) = self.successors(v).get(edge) {
+                    tarjan.calls.last_mut().unwrap().1 += 1;
+                    if !keep[w as usize] {
+                        continue;
+                    }
+                    if tarjan.index[w as usize] == Tarjan::UNVISITED {
+                        tarjan.enter(w);
+                    } else if tarjan.on_stack[w as usize] {
+                        tarjan.lower(v, tarjan.index[w as usize]);
+                    }
+                } else {
+                    tarjan.leave(v);
+                }
+            }
+        }
+        tarjan.components
+    }
+
+    /// The nodes in `keep` ordered so every edge points forward, or a cycle
+    /// among them.
+    fn topological_order(&self, keep: &[bool]) -> Result<Vec<u32>, Cycle> {
+        let components = self.strongly_connected_components(keep);
+        let mut order = Vec::with_capacity(components.len());
+        for component in components.iter().rev() {
+            if self.is_cyclic(component) {
+                return Err(self.cycle_within(component));
+            }
+            order.push(component[0]);
+        }
+        Ok(order)
+    }
+
+    fn is_cyclic(&self, component: &[u32]) -> bool {
+        component.len() > 1 || self.successors(component[0]).contains(&component[0])
+    }
+
+    /// A concrete cycle through the first node of a strongly connected
+    /// component, found by a breadth-first search that stays inside it.
+    fn cycle_within(&self, component: &[u32]) -> Cycle {
+        let start = component[0];
+        let mut inside = vec![false; self.len()];
+        component.iter().for_each(|&v| inside[v as usize] = true);
+
+        let mut parent: HashMap<u32, u32> = HashMap::new();
+        let mut queue = VecDeque::from([start]);
+        while let Some(v) = queue.pop_front() {
+            for &w in self.successors(v) {
+                if w == start {
+                    let mut path = vec![v];
+                    let mut at = v;
+                    while at != start {
+                        at = parent[&at];
+                        path.push(at);
+                    }
+                    path.reverse();
+                    path.push(start);
+                    return Cycle(path.iter().map(|&v| self.name(v).to_string()).collect());
+                }
+                if inside[w as usize] && !parent.contains_key(&w) {
+                    parent.insert(w, v);
+                    queue.push_back(w);
+                }
+            }
+        }
+        unreachable!("a strongly connected component always has a cycle")
+    }
+
+    /// Nodes lying on some path from `from` to `to` that only passes
+    /// through `allowed` nodes.
+    fn on_paths(&self, from: u32, to: u32, allowed: &[bool]) -> Vec<bool> {
+        if !allowed[from as usize] || !allowed[to as usize] {
+            return vec![false; self.len()];
+        }
+        let forward = self.reachable(from, false, allowed);
+        let backward = self.reachable(to, true, allowed);
+        forward
+            .iter()
+            .zip(&backward)
+            .map(|(a, b)| *a && *b)
+            .collect()
+    }
+
+    /// Number of distinct paths from `from` to `to` through `allowed` nodes.
+    fn count_paths<C: Counter>(
+        &self,
+        from: u32,
+        to: u32,
+        allowed: &[bool],
+        counter: &C,
+    ) -> Result<C::Value> {
+        let on_path = self.on_paths(from, to, allowed);
+        if !on_path[from as usize] {
+            return Ok(counter.zero());
+        }
+        let order = self
+            .topological_order(&on_path)
+            .with_context(|| self.describe(from, to))?;
+
+        let mut paths = vec![counter.zero(); self.len()];
+        paths[from as usize] = counter.one();
+        for &v in &order {
+            let count = paths[v as usize].clone();
+            for &w in self.successors(v) {
+                if on_path[w as usize] {
+                    paths[w as usize] = counter
+                        .add(&paths[w as usize], &count)
+                        .with_context(|| self.overflowed(from, to))?;
+                }
+            }
+        }
+        Ok(paths[to as usize].clone())
+    }
+
+    fn describe(&self, from: u32, to: u32) -> String {
+        format!(
+            "counting paths from {} to {}",
+            self.name(from),
+            self.name(to)
+        )
+    }
+
+    fn overflowed(&self, from: u32, to: u32) -> String {
+        format!(
+            "{} (try --exact or --modulo <prime>)",
+            self.describe(from, to)
+        )
+    }
+
+    /// Paths from every node to `to`, zero for nodes that can't reach it.
+    /// Unlike [`count_paths`](Self::count_paths) this tolerates cycles: nodes
+    /// on or leading into one that reaches `to` are left without a count.
+    fn path_counts_to<C: Counter>(&self, to: u32, counter: &C) -> Result<PathCounts<C::Value>> {
+        let reaches = self.reachable(to, true, &vec![true; self.len()]);
+        let mut result = PathCounts {
+            counts: vec![Some(counter.zero()); self.len()],
+            on_cycle: vec![false; self.len()],
+            cycles: Vec::new(),
+        };
+
+        // components come out with everything they point to already counted
+        for component in self.strongly_connected_components(&reaches) {
+            if self.is_cyclic(&component) {
+                result.cycles.push(self.cycle_within(&component));
+                for &v in &component {
+                    result.counts[v as usize] = None;
+                    result.on_cycle[v as usize] = true;
+                }
+                continue;
+            }
+            let v = component[0];
+            if v == to {
+                result.counts[v as usize] = Some(counter.one());
+                continue;
+            }
+            let mut total = Some(counter.zero());
+            for &w in self.successors(v) {
+                total = match (total, &result.counts[w as usize]) {
+                    (Some(total), Some(count)) => Some(
+                        counter
+                            .add(&total, count)
+                            .with_context(|| format!("counting paths to {}", self.name(to)))?,
+                    ),
+                    _ => None,
+                };
+            }
+            result.counts[v as usize] = total;
+        }
+        Ok(result)
+    }
 
-fn part2(graph: &HashMap<String, Vec<String>>) -> Result<u64> {
-    let start = "svr";
-    let end = "out";
+    /// Writes the graph in Graphviz DOT format. Every device is labelled with
+    /// its number of paths to `sink`; `sources`, `sink` and `waypoints` are
+    /// highlighted, devices no source reaches are greyed out and devices that
+    /// can't reach `sink` are drawn in red. Cycles don't stop the export:
+    /// devices on one are drawn in orange, and devices with infinitely many
+    /// paths are labelled `?`. Returns those cycles.
+    fn write_dot<W: Write>(
+        &self,
+        mut out: W,
+        sources: &[u32],
+        sink: u32,
+        waypoints: &[u32],
+    ) -> Result<Vec<Cycle>> {
+        // labels can't overflow, however many paths there are
+        let PathCounts {
+            counts,
+            on_cycle,
+            cycles,
+        } = self.path_counts_to(sink, &Exact)?;
+        let all = vec![true; self.len()];
+        let reaches_sink = self.reachable(sink, true, &all);
+        let mut reached = vec![false; self.len()];
+        for &source in sources {
+            let from_source = self.reachable(source, false, &all);
+            reached
+                .iter_mut()
+                .zip(from_source)
+                .for_each(|(r, s)| *r |= s);
+        }
+        let dead_end = |v: u32| !reaches_sink[v as usize];
+        let faded = |v: u32| !reached[v as usize] || dead_end(v);
 
-    // required nodes in a fixed order (bit 0 = dac, bit 1 = fft)
-    let required = ["dac", "fft"];
+        // device names can't contain whitespace, but quotes must be escaped
+        let id = |v: u32| self.name(v).replace('\\', "\\\\").replace('"', "\\\"");
 
-    let mut memo: HashMap<(String, u8), u64> = HashMap::new();
-    let paths = count_paths_required_memo(start, end, graph, 0, &required, &mut memo);
+        writeln!(out, "digraph devices {{")?;
+        writeln!(out, "    rankdir=LR;")?;
+        writeln!(out, "    node [shape=box, fontname=\"monospace\"];")?;
+        for v in 0..self.len() as u32 {
+            let mut styles = Vec::new();
+            let count = counts[v as usize]
+                .as_ref()
+                .map_or("?".to_string(), |c| c.to_string());
+            let mut attrs = vec![format!("label=\"{}\\n{count}\"", id(v))];
+            if sources.contains(&v) || v == sink {
+                attrs.push("shape=doubleoctagon".to_string());
+                styles.push("bold");
+            }
+            if waypoints.contains(&v) {
+                attrs.push("fillcolor=gold".to_string());
+                styles.push("filled");
+            }
+            if !reached[v as usize] {
+                attrs.push("fontcolor=gray50".to_string());
+                styles.push("dashed");
+            }
+            if on_cycle[v as usize] {
+                attrs.push("color=orange".to_string());
+            } else if dead_end(v) {
+                attrs.push("color=red".to_string());
+            } else if !reached[v as usize] {
+                attrs.push("color=gray50".to_string());
+            }
+            if !styles.is_empty() {
+                attrs.push(format!("style=\"{}\"", styles.join(",")));
+            }
+            writeln!(out, "    \"{}\" [{}];", id(v), attrs.join(", "))?;
+        }
+        for v in 0..self.len() as u32 {
+            for &w in self.successors(v) {
+                let colour = if faded(v) || faded(w) {
+                    " [color=gray70]"
+                } else {
+                    ""
+                };
+                writeln!(out, "    \"{}\" -> \"{}\"{colour};", id(v), id(w))?;
+            }
+        }
+        writeln!(out, "}}")?;
+        out.flush()?;
+        Ok(cycles)
+    }
+
+    /// Starts a query for the paths from `from` to `to`.
+    fn paths(&self, from: u32, to: u32) -> PathQuery<'_> {
+        PathQuery {
+            graph: self,
+            from,
+            to,
+            required: Vec::new(),
+            forbidden: Vec::new(),
+            sequences: Vec::new(),
+        }
+    }
+}
 
-    Ok(paths)
+/// Paths between two devices, narrowed down by constraints. Only the part of
+/// the graph lying on such paths matters; a cycle there would allow
+/// infinitely many paths, so it is reported instead of counted.
+struct PathQuery<'a> {
+    graph: &'a DeviceGraph,
+    from: u32,
+    to: u32,
+    required: Vec<u32>,
+    forbidden: Vec<u32>,
+    sequences: Vec<Vec<u32>>,
 }
 
-fn dfs_count(
-    current: &str,
-    end: &str,
-    graph: &HashMap<String, Vec<String>>,
-    visited: &mut HashSet<String>,
-) -> u64 {
-    if current == end {
-        return 1;
+impl PathQuery<'_> {
+    /// Only paths that visit `node`.
+    fn through(mut self, node: u32) -> Self {
+        self.required.push(node);
+        self
     }
 
-    if visited.contains(current) {
-        return 0;
+    /// Only paths that never visit `node`.
+    fn avoiding(mut self, node: u32) -> Self {
+        self.forbidden.push(node);
+        self
     }
 
-    visited.insert(current.to_string());
+    /// Only paths that visit all of `nodes`, in this order.
+    fn in_order(mut self, nodes: &[u32]) -> Self {
+        self.required.extend_from_slice(nodes);
+        self.sequences.push(nodes.to_vec());
+        self
+    }
 
-    let mut total_paths = 0;
+    /// Counts the matching paths in the arithmetic of `counter`, through a
+    /// [`PathTable`] when there are few enough required nodes and by
+    /// [factorising](Self::count_factorised) otherwise.
+    fn count_with<C: Counter>(&self, counter: &C) -> Result<C::Value> {
+        let layout = self.layout()?;
+        if layout.required.len() <= MAX_MASK_BITS
+            && layout.order.len() << layout.required.len() <= MAX_TABLE_ENTRIES
+        {
+            let table = self
+                .table_from(layout, counter)
+                .with_context(|| self.graph.overflowed(self.from, self.to))?;
+            Ok(table.total)
+        } else {
+            self.count_factorised(layout, counter)
+        }
+    }
 
-    if let Some(neighbors) = graph.get(current) {
-        for next in neighbors {
-            total_paths += dfs_count(next, end, graph, visited)
+    /// The per-node path counts behind [`count_with`](Self::count_with), in
+    /// `u64` for walking individual paths. Fails when the graph on the paths
+    /// has a cycle, a count overflows or the table would be too large.
+    fn table(&self) -> Result<PathTable<u64>> {
+        let layout = self.layout()?;
+        let (k, nodes) = (layout.required.len(), layout.order.len());
+        if k > MAX_MASK_BITS || nodes << k > MAX_TABLE_ENTRIES {
+            bail!("{k} required nodes over {nodes} devices need too large a table");
         }
+        self.table_from(layout, &Checked::<u64>::new())
+            .with_context(|| self.graph.describe(self.from, self.to))
     }
 
-    visited.remove(current);
+    fn layout(&self) -> Result<Layout> {
+        let graph = self.graph;
+        let mut allowed = vec![true; graph.len()];
+        self.forbidden
+            .iter()
+            .for_each(|&v| allowed[v as usize] = false);
+
+        let on_path = graph.on_paths(self.from, self.to, &allowed);
+        let order = graph
+            .topological_order(&on_path)
+            .with_context(|| graph.describe(self.from, self.to))?;
+        let mut required = self.required.clone();
+        required.sort_unstable();
+        required.dedup();
+
+        Ok(Layout {
+            allowed,
+            on_path,
+            order,
+            required,
+        })
+    }
+
+    /// Fills the table backwards from the end, in reverse topological order,
+    /// so no recursion is involved however deep the graph is.
+    fn table_from<C: Counter>(
+        &self,
+        layout: Layout,
+        counter: &C,
+    ) -> Result<PathTable<C::Value>, Overflow> {
+        let n = self.graph.len();
+        let stride = 1usize << layout.required.len();
+        let full = stride as u64 - 1;
+
+        let mut bits = vec![0u64; n];
+        for (i, &v) in layout.required.iter().enumerate() {
+            bits[v as usize] = 1 << i;
+        }
+        // entering a node in a sequence needs everything before it visited
+        let mut needs = vec![0u64; n];
+        for sequence in &self.sequences {
+            let mut before = 0;
+            for &v in sequence {
+                needs[v as usize] |= before;
+                before |= bits[v as usize];
+            }
+        }
+
+        // only nodes on the paths get rows, numbered by topological position
+        let mut slots = vec![PathTable::<C::Value>::OFF_PATH; n];
+        for (i, &v) in layout.order.iter().enumerate() {
+            slots[v as usize] = i as u32;
+        }
+
+        let mut table = PathTable {
+            from: self.from,
+            to: self.to,
+            stride,
+            counts: vec![counter.zero(); layout.order.len() * stride],
+            total: counter.zero(),
+            bits,
+            needs,
+            slots,
+        };
+
+        for &v in layout.order.iter().rev() {
+            let row = table.slots[v as usize] as usize * stride;
+            if v == self.to {
+                table.counts[row + full as usize] = counter.one();
+                continue;
+            }
+            for mask in 0..stride as u64 {
+                let mut total = counter.zero();
+                for &w in self.graph.successors(v) {
+                    if let Some(next) = table.enter(mask, w) {
+                        total = counter.add(&total, table.get(w, next))?;
+                    }
+                }
+                table.counts[row + mask as usize] = total;
+            }
+        }
+
+        if let Some(mask) = table.enter(0, self.from) {
+            table.total = table.get(self.from, mask).clone();
+        }
+        Ok(table)
+    }
+
+    /// A path meets nodes in topological order, so the required nodes can
+    /// only be visited sorted by their position in it. The count is then the
+    /// product of the path counts between consecutive stops, which works for
+    /// any number of required nodes.
+    fn count_factorised<C: Counter>(&self, layout: Layout, counter: &C) -> Result<C::Value> {
+        let graph = self.graph;
+        let on_path = &layout.on_path;
+        if layout.required.iter().any(|&v| !on_path[v as usize]) || !on_path[self.from as usize] {
+            return Ok(counter.zero());
+        }
+
+        let mut position = vec![usize::MAX; graph.len()];
+        for (i, &v) in layout.order.iter().enumerate() {
+            position[v as usize] = i;
+        }
+
+        let in_sequence = |seq: &Vec<u32>| {
+            seq.windows(2)
+                .all(|w| position[w[0] as usize] < position[w[1] as usize])
+        };
+        if !self.sequences.iter().all(in_sequence) {
+            return Ok(counter.zero());
+        }
+
+        let mut stops = vec![self.from];
+        stops.extend(layout.required.iter().copied());
+        stops.push(self.to);
+        stops.sort_by_key(|&v| position[v as usize]);
+        stops.dedup();
 
-    total_paths
+        let mut total = counter.one();
+        for leg in stops.windows(2) {
+            let paths = graph.count_paths(leg[0], leg[1], &layout.allowed, counter)?;
+            total = counter
+                .mul(&total, &paths)
+                .with_context(|| graph.overflowed(self.from, self.to))?;
+        }
+        Ok(total)
+    }
+}
+
+/// Most required nodes tracked as a bit mask per table entry.
+const MAX_MASK_BITS: usize = 16;
+/// Most entries a [`PathTable`] may hold.
+const MAX_TABLE_ENTRIES: usize = 1 << 26;
+
+/// What a [`PathQuery`] needs once its constraints are resolved.
+struct Layout {
+    allowed: Vec<bool>,
+    on_path: Vec<bool>,
+    /// The nodes of `on_path` in topological order.
+    order: Vec<u32>,
+    /// Sorted and without duplicates; node `required[i]` is bit `i`.
+    required: Vec<u32>,
+}
+
+/// For every node `v` and set of required nodes `mask`, the number of ways to
+/// finish a path from `v` to the end when `mask` has been visited so far
+/// (including `v`). Stored densely as `stride` counts per node on the paths.
+struct PathTable<V> {
+    from: u32,
+    to: u32,
+    stride: usize,
+    counts: Vec<V>,
+    /// The number of matching paths from the start.
+    total: V,
+    bits: Vec<u64>,
+    needs: Vec<u64>,
+    /// The row of every node in `counts`, or `OFF_PATH`.
+    slots: Vec<u32>,
+}
+
+impl<V> PathTable<V> {
+    const OFF_PATH: u32 = u32::MAX;
+
+    fn get(&self, v: u32, mask: u64) -> &V {
+        &self.counts[self.slots[v as usize] as usize * self.stride + mask as usize]
+    }
+
+    /// The visited set after stepping onto `w`, or `None` if `w` can't be
+    /// entered with `mask` visited.
+    fn enter(&self, mask: u64, w: u32) -> Option<u64> {
+        let needs = self.needs[w as usize];
+        (self.slots[w as usize] != Self::OFF_PATH && mask & needs == needs)
+            .then(|| mask | self.bits[w as usize])
+    }
+}
+
+impl PathTable<u64> {
+    /// The `k`-th matching path (from 0) in lexicographic order of device
+    /// names, or `None` if there are only `k` or fewer. Each step skips whole
+    /// blocks of paths using the counts, so no other path is visited.
+    fn nth(&self, graph: &DeviceGraph, mut k: u64) -> Option<Vec<u32>> {
+        if k >= self.total {
+            return None;
+        }
+        let mut v = self.from;
+        let mut mask = self.enter(0, v)?;
+        let mut path = vec![v];
+
+        while v != self.to {
+            let mut next = graph.successors(v).to_vec();
+            next.sort_unstable_by_key(|&w| graph.name(w));
+            for w in next {
+                let Some(after) = self.enter(mask, w) else {
+                    continue;
+                };
+                let count = *self.get(w, after);
+                if k < count {
+                    (v, mask) = (w, after);
+                    path.push(w);
+                    break;
+                }
+                k -= count;
+            }
+        }
+        Some(path)
+    }
 }
 
-fn count_paths_required_memo(
-    current: &str,
-    end: &str,
-    graph: &HashMap<String, Vec<String>>,
-    mut mask: u8,
-    required: &[&str; 2],
-    memo: &mut HashMap<(String, u8), u64>,
-) -> u64 {
-    for (i, &req) in required.iter().enumerate() {
-        if current == req {
-            mask |= 1 << i;
+/// State of Tarjan's algorithm, with the recursion kept on `calls` as
+/// `(node, next edge to follow)`.
+struct Tarjan {
+    index: Vec<u32>,
+    low: Vec<u32>,
+    on_stack: Vec<bool>,
+    stack: Vec<u32>,
+    calls: Vec<(u32, usize)>,
+    next_index: u32,
+    components: Vec<Vec<u32>>,
+}
+
+impl Tarjan {
+    const UNVISITED: u32 = u32::MAX;
+
+    fn new(n: usize) -> Self {
+        Tarjan {
+            index: vec![Self::UNVISITED; n],
+            low: vec![0; n],
+            on_stack: vec![false; n],
+            stack: Vec::new(),
+            calls: Vec::new(),
+            next_index: 0,
+            components: Vec::new(),
         }
     }
 
-    if current == end {
-        return if mask == 0b11 { 1 } else { 0 };
+    fn enter(&mut self, v: u32) {
+        self.index[v as usize] = self.next_index;
+        self.low[v as usize] = self.next_index;
+        self.next_index += 1;
+        self.on_stack[v as usize] = true;
+        self.stack.push(v);
+        self.calls.push((v, 0));
     }
-    if let Some(&cached) = memo.get(&(current.to_string(), mask)) {
-        return cached;
+
+    fn lower(&mut self, v: u32, to: u32) {
+        self.low[v as usize] = self.low[v as usize].min(to);
     }
 
-    let mut total = 0u64;
-    if let Some(neighbors) = graph.get(current) {
-        for next in neighbors {
-            total += count_paths_required_memo(next, end, graph, mask, required, memo);
+    /// Returns from `v`, closing its component if it is the root of one.
+    fn leave(&mut self, v: u32) {
+        self.calls.pop();
+        if let Some(&(parent, _)) = self.calls.last() {
+            self.lower(parent, self.low[v as usize]);
+        }
+        if self.low[v as usize] == self.index[v as usize] {
+            let mut component = Vec::new();
+            loop {
+                let w = self.stack.pop().unwrap();
+                self.on_stack[w as usize] = false;
+                component.push(w);
+                if w == v {
+                    break;
+                }
+            }
+            self.components.push(component);
         }
     }
+}
 
-    memo.insert((current.to_string(), mask), total);
-    total
+/// Compressed sparse rows for `n` nodes: the targets of node `v` are
+/// `targets[offsets[v]..offsets[v + 1]]`, in input order.
+fn csr(n: usize, edges: impl Iterator<Item = (u32, u32)> + Clone) -> (Vec<usize>, Vec<u32>) {
+    let mut offsets = vec![0usize; n + 1];
+    for (from, _) in edges.clone() {
+        offsets[from as usize + 1] += 1;
+    }
+    for v in 0..n {
+        offsets[v + 1] += offsets[v];
+    }
+    let mut fill = offsets.clone();
+    let mut targets = vec![0u32; offsets[n]];
+    for (from, to) in edges {
+        targets[fill[from as usize]] = to;
+        fill[from as usize] += 1;
+    }
+    (offsets, targets)
+}
+
+/// Path counts to one device, see [`DeviceGraph::path_counts_to`].
+struct PathCounts<V> {
+    /// `None` for devices with infinitely many paths.
+    counts: Vec<Option<V>>,
+    /// Devices in a strongly connected component with a cycle.
+    on_cycle: Vec<bool>,
+    /// One cycle from each such component.
+    cycles: Vec<Cycle>,
+}
+
+/// A directed cycle by device name, with the first device repeated at the end.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Cycle(Vec<String>);
+
+impl fmt::Display for Cycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the devices form a cycle: {}", self.0.join(" -> "))
+    }
 }
 
+impl std::error::Error for Cycle {}
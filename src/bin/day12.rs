@@ -1,53 +1,476 @@
-use std::fmt::DebugList;
-
-use anyhow::Result;
-use aoc_2025::{input_path, lines, read_to_string};
+use anyhow::{Context, Result, bail};
+use aoc_2025::packing::{Budget, Packing, Shape, pack_within};
+use aoc_2025::{input_path, read_to_string};
+use rayon::prelude::*;
+use serde_json::json;
+use std::fmt;
+use std::io::Write;
+use std::time::Duration;
 
 fn main() -> Result<()> {
     let input = read_to_string(input_path(12))?;
+    let puzzle = Puzzle::parse(&input)?;
+
+    // `--nodes <n>` and `--timeout <ms>` cap the search for each region;
+    // regions that run out are reported as unknown
+    let args: Vec<String> = std::env::args().collect();
+    let number = |flag: &str| -> Result<Option<u64>> {
+        match args.iter().position(|a| a == flag) {
+            Some(i) => {
+                let n = args
+                    .get(i + 1)
+                    .and_then(|n| n.parse().ok())
+                    .with_context(|| format!("{flag} takes a number"))?;
+                Ok(Some(n))
+            }
+            None => Ok(None),
+        }
+    };
+    let budget = Budget {
+        nodes: number("--nodes")?,
+        time: number("--timeout")?.map(Duration::from_millis),
+    };
+
+    // `--compare` checks the area heuristic against the exact packer
+    if args.iter().any(|a| a == "--compare") {
+        compare(&puzzle, budget);
+        return Ok(());
+    }
+
+    // `--render <region>` draws a packing of one region and lists its
+    // placements, as JSON with `--json`
+    if let Some(pos) = args.iter().position(|a| a == "--render") {
+        let index = args
+            .get(pos + 1)
+            .and_then(|a| a.parse::<usize>().ok())
+            .context("--render takes a region index")?;
+        let region = puzzle
+            .regions
+            .get(index)
+            .with_context(|| format!("there are only {} regions", puzzle.regions.len()))?;
+        let json = args.iter().any(|a| a == "--json");
+        return render(index, region, &puzzle.shapes, budget, json);
+    }
+
+    let verdicts = evaluate(&puzzle, budget);
+    println!("Part 1: {}", part1(&verdicts)?);
+
+    let count = |verdict: Verdict| verdicts.iter().filter(|(v, _)| *v == verdict).count();
+    println!(
+        "{} fit, {} do not fit, {} unknown",
+        count(Verdict::Fits),
+        count(Verdict::DoesNotFit),
+        count(Verdict::Unknown)
+    );
+    // `--verdicts` lists every region, `--tiers` how many each tier decided
+    if args.iter().any(|a| a == "--verdicts") {
+        for (region, (verdict, tier)) in puzzle.regions.iter().zip(&verdicts) {
+            println!(
+                "line {}: {}x{} {verdict} [{tier:?}]",
+                region.line, region.width, region.height
+            );
+        }
+    }
+    if args.iter().any(|a| a == "--tiers") {
+        report_tiers(&verdicts);
+    }
+    Ok(())
+}
+
+fn part1(verdicts: &[(Verdict, Tier)]) -> Result<usize> {
+    let result = verdicts
+        .iter()
+        .filter(|(verdict, _)| *verdict == Verdict::Fits)
+        .count();
+
+    Ok(result)
+}
+
+/// Classifies every region, in parallel since they are independent.
+fn evaluate(puzzle: &Puzzle, budget: Budget) -> Vec<(Verdict, Tier)> {
+    puzzle
+        .regions
+        .par_iter()
+        .map(|region| region.classify(&puzzle.shapes, budget))
+        .collect()
+}
+
+fn report_tiers(verdicts: &[(Verdict, Tier)]) {
+    let (mut blocks, mut cells, mut searched, mut packed, mut unknown) = (0, 0, 0, 0, 0);
+    for (verdict, tier) in verdicts {
+        match tier {
+            Tier::Blocks => blocks += 1,
+            Tier::Cells => cells += 1,
+            Tier::Search => {
+                searched += 1;
+                packed += (*verdict == Verdict::Fits) as usize;
+                unknown += (*verdict == Verdict::Unknown) as usize;
+            }
+        }
+    }
+    println!("Accepted by whole blocks: {blocks}");
+    println!("Rejected by cell count: {cells}");
+    println!("Searched: {searched} ({packed} fit, {unknown} unknown)");
+}
+
+/// Packs `region` and draws it with every cell labelled by its piece: the
+/// shape index followed by letters numbering the pieces of that shape.
+/// The placements follow, so the packing can be checked independently.
+fn render(
+    index: usize,
+    region: &Region,
+    shapes: &[Shape],
+    budget: Budget,
+    json: bool,
+) -> Result<()> {
+    let mut out = std::io::stdout().lock();
+    let (verdict, packing) = match region.pack(shapes, budget) {
+        Packing::Fits(placements) => (Verdict::Fits, Some(placements)),
+        Packing::DoesNotFit => (Verdict::DoesNotFit, None),
+        Packing::Unknown => (Verdict::Unknown, None),
+    };
+
+    let Some(placements) = packing else {
+        if json {
+            let value = json!({
+                "region": index,
+                "line": region.line,
+                "width": region.width,
+                "height": region.height,
+                "verdict": verdict.to_string(),
+            });
+            serde_json::to_writer_pretty(&mut out, &value)?;
+            writeln!(out)?;
+        } else {
+            writeln!(
+                out,
+                "Region {index} (line {}): {}x{} {verdict}",
+                region.line, region.width, region.height
+            )?;
+        }
+        return Ok(());
+    };
+
+    let mut instances = vec![0; shapes.len()];
+    let labels: Vec<String> = placements
+        .iter()
+        .map(|p| {
+            instances[p.shape] += 1;
+            format!("{}{}", p.shape, instance_name(instances[p.shape] - 1))
+        })
+        .collect();
+    let mut grid: Vec<Option<usize>> = vec![None; region.width * region.height];
+    for (i, placement) in placements.iter().enumerate() {
+        for (x, y) in placement.cells(shapes) {
+            grid[y * region.width + x] = Some(i);
+        }
+    }
+    let cell_width = labels.iter().map(String::len).max().unwrap_or(1);
+    let rows: Vec<String> = grid
+        .chunks(region.width.max(1))
+        .map(|row| {
+            row.iter()
+                .map(|cell| match cell {
+                    Some(i) => format!("{:<cell_width$}", labels[*i]),
+                    None => format!("{:<cell_width$}", "."),
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+                .trim_end()
+                .to_string()
+        })
+        .collect();
 
-    println!("Part 1: {}", part1(&input)?);
-  
+    if json {
+        let pieces: Vec<_> = placements
+            .iter()
+            .zip(&labels)
+            .map(|(p, label)| {
+                json!({
+                    "label": label,
+                    "shape": p.shape,
+                    "orientation": p.orientation,
+                    "x": p.x,
+                    "y": p.y,
+                    "cells": p.cells(shapes),
+                })
+            })
+            .collect();
+        let value = json!({
+            "region": index,
+            "line": region.line,
+            "width": region.width,
+            "height": region.height,
+            "verdict": verdict.to_string(),
+            "grid": rows,
+            "placements": pieces,
+        });
+        serde_json::to_writer_pretty(&mut out, &value)?;
+        writeln!(out)?;
+        return Ok(());
+    }
+
+    writeln!(
+        out,
+        "Region {index} (line {}): {}x{} with {} pieces",
+        region.line,
+        region.width,
+        region.height,
+        placements.len()
+    )?;
+    for row in &rows {
+        writeln!(out, "{row}")?;
+    }
+    writeln!(out)?;
+    writeln!(out, "piece shape orientation x y")?;
+    for (p, label) in placements.iter().zip(&labels) {
+        writeln!(out, "{label} {} {} {} {}", p.shape, p.orientation, p.x, p.y)?;
+    }
     Ok(())
 }
 
+/// `a` to `z` for the first pieces of a shape, then `aa`, `ab` and so on.
+fn instance_name(mut n: usize) -> String {
+    let mut letters = Vec::new();
+    loop {
+        letters.push(b'a' + (n % 26) as u8);
+        if n < 26 {
+            break;
+        }
+        n = n / 26 - 1;
+    }
+    letters.iter().rev().map(|&b| b as char).collect()
+}
 
-fn part1(input: &str) -> Result<i32> {
-  let result = input
-    .as_bytes()
-    .split(|&b| b == b'\n')
-    .skip(6 * 5)
-    .filter(|region| region_is_valid(region))
-    .count();
+/// Prints how often the heuristic agrees with the packer, and every region
+/// where it doesn't. Regions the packer couldn't decide are left out.
+fn compare(puzzle: &Puzzle, budget: Budget) {
+    let exact: Vec<Verdict> = puzzle
+        .regions
+        .par_iter()
+        .map(|region| region.pack(&puzzle.shapes, budget).into())
+        .collect();
 
-    Ok(result as i32)
+    let (mut agree, mut decided) = (0, 0);
+    for (region, &exact) in puzzle.regions.iter().zip(&exact) {
+        if exact == Verdict::Unknown {
+            continue;
+        }
+        decided += 1;
+        let guess = match region_is_valid(region, &puzzle.shapes) {
+            true => Verdict::Fits,
+            false => Verdict::DoesNotFit,
+        };
+        if guess == exact {
+            agree += 1;
+        } else {
+            println!(
+                "line {}: {}x{} {guess} by the heuristic but {exact} when packed",
+                region.line, region.width, region.height
+            );
+        }
+    }
+    println!("The heuristic agrees with the packer on {agree} of {decided} decided regions");
+    if decided < exact.len() {
+        println!("{} regions ran out of budget", exact.len() - decided);
+    }
 }
 
+/// The original estimate: every piece takes up its whole bounding box.
+fn region_is_valid(region: &Region, shapes: &[Shape]) -> bool {
+    let required: usize = region
+        .counts
+        .iter()
+        .zip(shapes)
+        .map(|(count, shape)| count * shape.width() * shape.height())
+        .sum();
+    region.width * region.height >= required
+}
 
-fn region_is_valid(region: &[u8]) -> bool {
-    let (size_part, counts_part) = split_size_and_counts(region);
+struct Puzzle {
+    shapes: Vec<Shape>,
+    regions: Vec<Region>,
+}
 
-    let area = parse_dimensions(size_part);
-    let required = parse_required(counts_part);
+/// A `width`×`height` region that must hold `counts[i]` copies of shape `i`.
+struct Region {
+    /// 1-based line in the input, for messages.
+    line: usize,
+    width: usize,
+    height: usize,
+    counts: Vec<usize>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Verdict {
+    Fits,
+    DoesNotFit,
+    /// The search ran out of budget.
+    Unknown,
+}
+
+impl From<Packing> for Verdict {
+    fn from(packing: Packing) -> Self {
+        match packing {
+            Packing::Fits(_) => Verdict::Fits,
+            Packing::DoesNotFit => Verdict::DoesNotFit,
+            Packing::Unknown => Verdict::Unknown,
+        }
+    }
+}
 
-    area >= required
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Fits => write!(f, "fits"),
+            Verdict::DoesNotFit => write!(f, "does not fit"),
+            Verdict::Unknown => write!(f, "unknown (budget exceeded)"),
+        }
+    }
 }
 
-fn split_size_and_counts(region: &[u8]) -> (&[u8], &[u8]) {
-    let colon_pos = region.iter().position(|&b| b == b':').unwrap();
-    region.split_at(colon_pos)
+/// Which check of [`Region::classify`] settled a region, cheapest first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Tier {
+    /// Every piece got a block of its own, so the region fits.
+    Blocks,
+    /// The pieces have more cells than the region, so it doesn't.
+    Cells,
+    /// Neither bound applied and the packer decided.
+    Search,
 }
 
-fn parse_dimensions(size: &[u8]) -> usize {
-    size.split(|&b| b == b'x')
-        .map(|n| atoi::atoi::<usize>(n).unwrap())
-        .product()
+impl Region {
+    fn pack(&self, shapes: &[Shape], budget: Budget) -> Packing {
+        pack_within(self.width, self.height, shapes, &self.counts, budget)
+    }
+
+    /// Whether the pieces fit, and which tier found out. Tiling the region
+    /// with blocks as large as the biggest bounding box and giving each piece
+    /// its own block proves a fit; more piece cells than region cells rules
+    /// one out. Only the band in between is searched, within `budget`.
+    fn classify(&self, shapes: &[Shape], budget: Budget) -> (Verdict, Tier) {
+        let block_width = shapes.iter().map(Shape::width).max().unwrap_or(0);
+        let block_height = shapes.iter().map(Shape::height).max().unwrap_or(0);
+        let pieces: usize = self.counts.iter().sum();
+        if block_width > 0
+            && block_height > 0
+            && (self.width / block_width) * (self.height / block_height) >= pieces
+        {
+            return (Verdict::Fits, Tier::Blocks);
+        }
+
+        let cells: usize = self
+            .counts
+            .iter()
+            .zip(shapes)
+            .map(|(count, shape)| count * shape.len())
+            .sum();
+        if cells > self.width * self.height {
+            return (Verdict::DoesNotFit, Tier::Cells);
+        }
+
+        (self.pack(shapes, budget).into(), Tier::Search)
+    }
 }
 
-fn parse_required(counts: &[u8]) -> usize {
-    counts[3..] // skip ": "
-        .split(|&b| b == b' ')
-        .map(|n| atoi::atoi::<usize>(n).unwrap() * 9)
-        .sum()
+impl Puzzle {
+    /// Parses the shape catalogue (`index:` followed by rows of `#` and `.`)
+    /// and the `WxH: count count ...` region lines after it. Shapes may have
+    /// any size, but their indices must run from 0 without gaps or repeats,
+    /// and every region needs one count per shape.
+    fn parse(input: &str) -> Result<Self> {
+        let mut shapes: Vec<Option<(usize, Shape)>> = Vec::new();
+        let mut regions = Vec::new();
+        // the shape being read: its index, header line and rows so far
+        let mut current: Option<(usize, usize, Vec<&str>)> = None;
+
+        let mut finish = |(index, line, rows): (usize, usize, Vec<&str>)| -> Result<()> {
+            let shape = shape_from_rows(&rows);
+            if shape.is_empty() {
+                bail!("shape {index} on line {line} has no cells");
+            }
+            if shapes.len() <= index {
+                shapes.resize(index + 1, None);
+            }
+            if let Some((first, _)) = &shapes[index] {
+                bail!("shape {index} is defined on lines {first} and {line}");
+            }
+            shapes[index] = Some((line, shape));
+            Ok(())
+        };
+
+        for (i, raw) in input.lines().enumerate() {
+            let line = raw.trim();
+            if let Some((_, _, rows)) = current.as_mut() {
+                if !line.is_empty() {
+                    if let Some(c) = line.chars().find(|&c| c != '#' && c != '.') {
+                        bail!("line {}: unexpected {c:?} in a shape", i + 1);
+                    }
+                    rows.push(line);
+                    continue;
+                }
+                finish(current.take().unwrap())?;
+            }
+            if line.is_empty() {
+                continue;
+            }
+
+            let (head, tail) = line
+                .split_once(':')
+                .with_context(|| format!("line {} has no ':': {line:?}", i + 1))?;
+            let number = |s: &str| {
+                s.trim()
+                    .parse::<usize>()
+                    .with_context(|| format!("line {}: bad number {s:?}", i + 1))
+            };
+            if let Some((w, h)) = head.split_once('x') {
+                regions.push(Region {
+                    line: i + 1,
+                    width: number(w)?,
+                    height: number(h)?,
+                    counts: tail.split_whitespace().map(number).collect::<Result<_>>()?,
+                });
+            } else {
+                if !tail.trim().is_empty() {
+                    bail!("line {}: expected the shape's rows below {head}:", i + 1);
+                }
+                current = Some((number(head)?, i + 1, Vec::new()));
+            }
+        }
+        if let Some(shape) = current {
+            finish(shape)?;
+        }
+
+        let shapes = shapes
+            .into_iter()
+            .enumerate()
+            .map(|(index, shape)| {
+                shape
+                    .map(|(_, s)| s)
+                    .with_context(|| format!("shape {index} is missing"))
+            })
+            .collect::<Result<Vec<Shape>>>()?;
+        for region in &regions {
+            if region.counts.len() != shapes.len() {
+                bail!(
+                    "line {}: {} counts given for {} shapes",
+                    region.line,
+                    region.counts.len(),
+                    shapes.len()
+                );
+            }
+        }
+
+        Ok(Puzzle { shapes, regions })
+    }
 }
 
+fn shape_from_rows(rows: &[&str]) -> Shape {
+    Shape::new(rows.iter().enumerate().flat_map(|(y, row)| {
+        row.bytes()
+            .enumerate()
+            .filter(|&(_, b)| b == b'#')
+            .map(move |(x, _)| (x, y))
+    }))
+}
@@ -0,0 +1,182 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use num_bigint::BigUint;
+
+/// Returned when a count no longer fits in the chosen integer type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Overflow;
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "count overflowed its integer type")
+    }
+}
+
+impl std::error::Error for Overflow {}
+
+/// The arithmetic used to accumulate path or timeline counts.
+pub trait Counter: Sync {
+    type Value: Clone + Send + Sync;
+
+    fn zero(&self) -> Self::Value;
+    fn one(&self) -> Self::Value;
+    fn add(&self, a: &Self::Value, b: &Self::Value) -> Result<Self::Value, Overflow>;
+    fn mul(&self, a: &Self::Value, b: &Self::Value) -> Result<Self::Value, Overflow>;
+}
+
+/// Fixed-width counts that report overflow instead of wrapping.
+pub struct Checked<T>(PhantomData<fn() -> T>);
+
+impl<T> Checked<T> {
+    pub const fn new() -> Self {
+        Checked(PhantomData)
+    }
+}
+
+impl<T> Default for Checked<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+macro_rules! impl_checked {
+    ($($t:ty),*) => {
+        $(
+            impl Counter for Checked<$t> {
+                type Value = $t;
+
+                fn zero(&self) -> $t {
+                    0
+                }
+
+                fn one(&self) -> $t {
+                    1
+                }
+
+                fn add(&self, a: &$t, b: &$t) -> Result<$t, Overflow> {
+                    a.checked_add(*b).ok_or(Overflow)
+                }
+
+                fn mul(&self, a: &$t, b: &$t) -> Result<$t, Overflow> {
+                    a.checked_mul(*b).ok_or(Overflow)
+                }
+            }
+        )*
+    };
+}
+
+impl_checked!(u64, u128, i128);
+
+/// Arbitrary-precision counts, these never overflow.
+pub struct Exact;
+
+impl Counter for Exact {
+    type Value = BigUint;
+
+    fn zero(&self) -> BigUint {
+        BigUint::ZERO
+    }
+
+    fn one(&self) -> BigUint {
+        BigUint::from(1u8)
+    }
+
+    fn add(&self, a: &BigUint, b: &BigUint) -> Result<BigUint, Overflow> {
+        Ok(a + b)
+    }
+
+    fn mul(&self, a: &BigUint, b: &BigUint) -> Result<BigUint, Overflow> {
+        Ok(a * b)
+    }
+}
+
+/// Counts reduced modulo a prime.
+pub struct Modular {
+    modulus: u64,
+}
+
+impl Modular {
+    /// Returns `None` when `modulus` is not prime.
+    pub fn new(modulus: u64) -> Option<Self> {
+        is_prime(modulus).then_some(Modular { modulus })
+    }
+
+    pub fn modulus(&self) -> u64 {
+        self.modulus
+    }
+}
+
+impl Counter for Modular {
+    type Value = u64;
+
+    fn zero(&self) -> u64 {
+        0
+    }
+
+    fn one(&self) -> u64 {
+        1 % self.modulus
+    }
+
+    fn add(&self, a: &u64, b: &u64) -> Result<u64, Overflow> {
+        Ok(((*a as u128 + *b as u128) % self.modulus as u128) as u64)
+    }
+
+    fn mul(&self, a: &u64, b: &u64) -> Result<u64, Overflow> {
+        Ok(mul_mod(*a, *b, self.modulus))
+    }
+}
+
+/// Deterministic Miller-Rabin, exact for every `u64`.
+fn is_prime(n: u64) -> bool {
+    const WITNESSES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+    if n < 2 {
+        return false;
+    }
+    for &p in &WITNESSES {
+        if n.is_multiple_of(p) {
+            return n == p;
+        }
+    }
+
+    let mut d = n - 1;
+    let mut s = 0;
+    while d.is_multiple_of(2) {
+        d /= 2;
+        s += 1;
+    }
+
+    'witness: for &a in &WITNESSES {
+        let mut x = pow_mod(a, d, n);
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+
+    true
+}
+
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    ((a as u128 * b as u128) % m as u128) as u64
+}
+
+fn pow_mod(mut base: u64, mut exp: u64, m: u64) -> u64 {
+    let mut result = 1;
+    base %= m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+    result
+}
@@ -0,0 +1,110 @@
+/// Union-find with union by size and path compression.
+#[derive(Clone, Debug)]
+pub struct DisjointSet {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+    components: usize,
+}
+
+impl DisjointSet {
+    pub fn new(n: usize) -> Self {
+        DisjointSet {
+            parent: (0..n).collect(),
+            size: vec![1; n],
+            components: n,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    pub fn component_count(&self) -> usize {
+        self.components
+    }
+
+    pub fn find(&mut self, x: usize) -> usize {
+        let mut root = x;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+
+        let mut current = x;
+        while self.parent[current] != root {
+            let next = self.parent[current];
+            self.parent[current] = root;
+            current = next;
+        }
+
+        root
+    }
+
+    /// Merges the components of `a` and `b`, returning `false` if they
+    /// were already connected.
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let mut ra = self.find(a);
+        let mut rb = self.find(b);
+
+        if ra == rb {
+            return false;
+        }
+
+        if self.size[ra] < self.size[rb] {
+            std::mem::swap(&mut ra, &mut rb);
+        }
+
+        self.parent[rb] = ra;
+        self.size[ra] += self.size[rb];
+        self.components -= 1;
+        true
+    }
+
+    pub fn connected(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// Size of the component containing `x`.
+    pub fn size_of(&mut self, x: usize) -> usize {
+        let root = self.find(x);
+        self.size[root]
+    }
+
+    /// Sizes of all components, in no particular order.
+    pub fn component_sizes(&self) -> Vec<usize> {
+        (0..self.parent.len())
+            .filter(|&i| self.parent[i] == i)
+            .map(|i| self.size[i])
+            .collect()
+    }
+
+    /// The `k` largest component sizes, largest first. Shorter than `k` when
+    /// there are fewer components.
+    pub fn top_sizes(&self, k: usize) -> Vec<usize> {
+        let mut sizes = self.component_sizes();
+        sizes.sort_unstable_by(|a, b| b.cmp(a));
+        sizes.truncate(k);
+        sizes
+    }
+
+    /// Members of every component, each list in increasing order. Components
+    /// are ordered by their smallest member.
+    pub fn components(&mut self) -> Vec<Vec<usize>> {
+        let mut slot = vec![usize::MAX; self.parent.len()];
+        let mut components: Vec<Vec<usize>> = Vec::with_capacity(self.components);
+
+        for i in 0..self.parent.len() {
+            let root = self.find(i);
+            if slot[root] == usize::MAX {
+                slot[root] = components.len();
+                components.push(Vec::with_capacity(self.size[root]));
+            }
+            components[slot[root]].push(i);
+        }
+
+        components
+    }
+}
@@ -0,0 +1,881 @@
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Point {
+    pub x: i64,
+    pub y: i64,
+}
+
+impl Point {
+    pub const fn new(x: i64, y: i64) -> Self {
+        Point { x, y }
+    }
+}
+
+/// A closed axis-aligned rectangle; `min` and `max` are both included.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Rect {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl Rect {
+    /// The rectangle spanned by two opposite corners, in any order.
+    pub fn from_corners(a: Point, b: Point) -> Self {
+        Rect {
+            min: Point::new(a.x.min(b.x), a.y.min(b.y)),
+            max: Point::new(a.x.max(b.x), a.y.max(b.y)),
+        }
+    }
+
+    pub fn contains(&self, p: Point) -> bool {
+        (self.min.x..=self.max.x).contains(&p.x) && (self.min.y..=self.max.y).contains(&p.y)
+    }
+
+    /// Number of lattice points (tiles) in the rectangle, edges included.
+    pub fn tile_count(&self) -> i128 {
+        (self.max.x as i128 - self.min.x as i128 + 1)
+            * (self.max.y as i128 - self.min.y as i128 + 1)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PolygonError {
+    TooFewVertices(usize),
+    /// The edge leaving vertex `index` is neither horizontal nor vertical.
+    NotAxisAligned {
+        index: usize,
+    },
+    /// Vertex `index` repeats the vertex before it.
+    ZeroLengthEdge {
+        index: usize,
+    },
+    /// The edges leaving vertices `first` and `second` touch or overlap.
+    SelfIntersecting {
+        first: usize,
+        second: usize,
+    },
+}
+
+impl fmt::Display for PolygonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolygonError::TooFewVertices(n) => {
+                write!(
+                    f,
+                    "a rectilinear polygon needs at least 4 vertices, got {n}"
+                )
+            }
+            PolygonError::NotAxisAligned { index } => {
+                write!(f, "edge from vertex {index} is not axis-aligned")
+            }
+            PolygonError::ZeroLengthEdge { index } => {
+                write!(f, "vertex {index} repeats the previous vertex")
+            }
+            PolygonError::SelfIntersecting { first, second } => {
+                write!(f, "edges from vertices {first} and {second} intersect")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PolygonError {}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Location {
+    Inside,
+    Boundary,
+    Outside,
+}
+
+/// A simple polygon whose edges are all horizontal or vertical. The last
+/// vertex connects back to the first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RectilinearPolygon {
+    vertices: Vec<Point>,
+}
+
+impl RectilinearPolygon {
+    /// Validates that the vertices form a closed, axis-aligned polygon that
+    /// does not touch itself. Repeating the first vertex at the end is allowed.
+    pub fn new(mut vertices: Vec<Point>) -> Result<Self, PolygonError> {
+        if vertices.len() > 1 && vertices.first() == vertices.last() {
+            vertices.pop();
+        }
+        let n = vertices.len();
+        if n < 4 {
+            return Err(PolygonError::TooFewVertices(n));
+        }
+
+        for i in 0..n {
+            let (a, b) = (vertices[i], vertices[(i + 1) % n]);
+            if a == b {
+                return Err(PolygonError::ZeroLengthEdge { index: (i + 1) % n });
+            }
+            if a.x != b.x && a.y != b.y {
+                return Err(PolygonError::NotAxisAligned { index: i });
+            }
+        }
+
+        let polygon = RectilinearPolygon { vertices };
+        let edges: Vec<(Point, Point)> = polygon.edges().collect();
+        for i in 0..n {
+            // an edge folding back onto the one before it
+            let (prev, next) = (edges[(i + n - 1) % n], edges[i]);
+            let d1 = (prev.1.x - prev.0.x, prev.1.y - prev.0.y);
+            let d2 = (next.1.x - next.0.x, next.1.y - next.0.y);
+            if d1.0 * d2.0 < 0 || d1.1 * d2.1 < 0 {
+                return Err(PolygonError::SelfIntersecting {
+                    first: (i + n - 1) % n,
+                    second: i,
+                });
+            }
+
+            for j in (i + 2)..n {
+                if i == 0 && j == n - 1 {
+                    continue;
+                }
+                if segments_touch(edges[i], edges[j]) {
+                    return Err(PolygonError::SelfIntersecting {
+                        first: i,
+                        second: j,
+                    });
+                }
+            }
+        }
+
+        Ok(polygon)
+    }
+
+    pub fn vertices(&self) -> &[Point] {
+        &self.vertices
+    }
+
+    /// Every edge as `(start, end)`, including the one closing the loop.
+    pub fn edges(&self) -> impl Iterator<Item = (Point, Point)> + '_ {
+        let n = self.vertices.len();
+        (0..n).map(move |i| (self.vertices[i], self.vertices[(i + 1) % n]))
+    }
+
+    pub fn bounding_box(&self) -> Rect {
+        let xs = self.vertices.iter().map(|p| p.x);
+        let ys = self.vertices.iter().map(|p| p.y);
+        Rect {
+            min: Point::new(xs.clone().min().unwrap(), ys.clone().min().unwrap()),
+            max: Point::new(xs.max().unwrap(), ys.max().unwrap()),
+        }
+    }
+
+    /// Enclosed area, via the shoelace formula.
+    pub fn area(&self) -> i128 {
+        let twice: i128 = self
+            .edges()
+            .map(|(a, b)| a.x as i128 * b.y as i128 - b.x as i128 * a.y as i128)
+            .sum();
+        twice.abs() / 2
+    }
+
+    /// Lattice points on the boundary, which for axis-aligned edges is the
+    /// perimeter.
+    pub fn boundary_points(&self) -> i128 {
+        self.edges()
+            .map(|(a, b)| (a.x as i128 - b.x as i128).abs() + (a.y as i128 - b.y as i128).abs())
+            .sum()
+    }
+
+    /// Lattice points strictly inside, from Pick's theorem `A = I + B/2 - 1`.
+    pub fn interior_points(&self) -> i128 {
+        self.area() - self.boundary_points() / 2 + 1
+    }
+
+    /// Lattice points inside or on the polygon.
+    pub fn lattice_points(&self) -> i128 {
+        self.interior_points() + self.boundary_points()
+    }
+
+    pub fn locate(&self, p: Point) -> Location {
+        let mut crossings = 0;
+        for (a, b) in self.edges() {
+            if Rect::from_corners(a, b).contains(p) {
+                return Location::Boundary;
+            }
+            // cast a ray towards +x; the half-open range counts each vertex once
+            if a.x == b.x && a.x > p.x && (a.y.min(b.y)..a.y.max(b.y)).contains(&p.y) {
+                crossings += 1;
+            }
+        }
+        if crossings % 2 == 1 {
+            Location::Inside
+        } else {
+            Location::Outside
+        }
+    }
+
+    /// Whether `p` is inside or on the polygon.
+    pub fn contains(&self, p: Point) -> bool {
+        self.locate(p) != Location::Outside
+    }
+
+    /// The parts of the polygon inside `rect`. Clipping a concave polygon can
+    /// split it, so this returns one polygon per piece, each counter-clockwise.
+    /// Pieces that only touch `rect` along an edge or corner are dropped.
+    pub fn clip(&self, rect: &Rect) -> Vec<RectilinearPolygon> {
+        let bounds = self.bounding_box();
+        let clamp_x = |x: i64| x.clamp(bounds.min.x, bounds.max.x);
+        let clamp_y = |y: i64| y.clamp(bounds.min.y, bounds.max.y);
+
+        let mut xs: Vec<i64> = self.vertices.iter().map(|p| p.x).collect();
+        let mut ys: Vec<i64> = self.vertices.iter().map(|p| p.y).collect();
+        xs.extend([clamp_x(rect.min.x), clamp_x(rect.max.x)]);
+        ys.extend([clamp_y(rect.min.y), clamp_y(rect.max.y)]);
+        xs.sort_unstable();
+        xs.dedup();
+        ys.sort_unstable();
+        ys.dedup();
+
+        let (w, h) = (xs.len() - 1, ys.len() - 1);
+        let mut filled = vec![false; w * h];
+        for j in 0..h {
+            let crossings = self.vertical_crossings(ys[j], ys[j + 1]);
+            for i in 0..w {
+                let in_rect = xs[i] >= rect.min.x
+                    && xs[i + 1] <= rect.max.x
+                    && ys[j] >= rect.min.y
+                    && ys[j + 1] <= rect.max.y;
+                // an odd number of crossings left of the cell means it's inside
+                let left = crossings.partition_point(|&x| x <= xs[i]);
+                filled[j * w + i] = in_rect && left % 2 == 1;
+            }
+        }
+
+        trace_cells(&filled, w, h)
+            .into_iter()
+            .map(|ring| RectilinearPolygon {
+                vertices: ring
+                    .into_iter()
+                    .map(|(i, j)| Point::new(xs[i], ys[j]))
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Sorted x of every vertical edge spanning the band from `y0` to `y1`,
+    /// where no vertex lies strictly inside the band.
+    fn vertical_crossings(&self, y0: i64, y1: i64) -> Vec<i64> {
+        let mut xs: Vec<i64> = self
+            .edges()
+            .filter(|(a, b)| a.x == b.x && a.y.min(b.y) <= y0 && a.y.max(b.y) >= y1)
+            .map(|(a, _)| a.x)
+            .collect();
+        xs.sort_unstable();
+        xs
+    }
+
+    /// Index over the tiles inside or on the polygon, for rectangle queries.
+    pub fn tile_grid(&self) -> TileGrid {
+        TileGrid::new(self)
+    }
+}
+
+fn segments_touch(e: (Point, Point), f: (Point, Point)) -> bool {
+    let (e, f) = (Rect::from_corners(e.0, e.1), Rect::from_corners(f.0, f.1));
+    e.min.x.max(f.min.x) <= e.max.x.min(f.max.x) && e.min.y.max(f.min.y) <= e.max.y.min(f.max.y)
+}
+
+/// Walks the outline of a set of grid cells into counter-clockwise rings of
+/// corner indices, one per piece. Collinear corners are dropped.
+fn trace_cells(filled: &[bool], w: usize, h: usize) -> Vec<Vec<(usize, usize)>> {
+    let cell = |i: isize, j: isize| {
+        i >= 0
+            && j >= 0
+            && (i as usize) < w
+            && (j as usize) < h
+            && filled[j as usize * w + i as usize]
+    };
+
+    // every cell side facing an empty neighbour, directed with the cell on its left
+    let mut outgoing: HashMap<(usize, usize), Vec<(usize, usize)>> = HashMap::new();
+    for j in 0..h {
+        for i in 0..w {
+            if !filled[j * w + i] {
+                continue;
+            }
+            let (ci, cj) = (i as isize, j as isize);
+            if !cell(ci, cj - 1) {
+                outgoing.entry((i, j)).or_default().push((i + 1, j));
+            }
+            if !cell(ci + 1, cj) {
+                outgoing.entry((i + 1, j)).or_default().push((i + 1, j + 1));
+            }
+            if !cell(ci, cj + 1) {
+                outgoing.entry((i + 1, j + 1)).or_default().push((i, j + 1));
+            }
+            if !cell(ci - 1, cj) {
+                outgoing.entry((i, j + 1)).or_default().push((i, j));
+            }
+        }
+    }
+
+    let mut starts: Vec<(usize, usize)> = outgoing.keys().copied().collect();
+    starts.sort_unstable();
+
+    let mut rings = Vec::new();
+    for start in starts {
+        while let Some(first) = outgoing.get_mut(&start).and_then(|v| v.pop()) {
+            let mut ring = vec![start];
+            let (mut from, mut at) = (start, first);
+            while at != start {
+                ring.push(at);
+                let options = outgoing.get_mut(&at).unwrap();
+                // where two pieces meet at a corner, turning left keeps them apart
+                let heading = direction(from, at);
+                let pick = (0..options.len())
+                    .max_by_key(|&k| turn(heading, direction(at, options[k])))
+                    .unwrap();
+                let next = options.swap_remove(pick);
+                (from, at) = (at, next);
+            }
+            rings.push(simplify(ring));
+        }
+    }
+    rings
+}
+
+fn direction(a: (usize, usize), b: (usize, usize)) -> (i64, i64) {
+    (
+        (b.0 as i64 - a.0 as i64).signum(),
+        (b.1 as i64 - a.1 as i64).signum(),
+    )
+}
+
+/// 1 for a left turn, 0 for straight on, -1 for a right turn.
+fn turn(from: (i64, i64), to: (i64, i64)) -> i64 {
+    from.0 * to.1 - from.1 * to.0
+}
+
+fn simplify(ring: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    let n = ring.len();
+    (0..n)
+        .filter(|&k| {
+            let (prev, here, next) = (ring[(k + n - 1) % n], ring[k], ring[(k + 1) % n]);
+            direction(prev, here) != direction(here, next)
+        })
+        .map(|k| ring[k])
+        .collect()
+}
+
+/// Which tiles (lattice points) lie inside or on a rectilinear polygon.
+///
+/// Tiles only change colour at the coordinates of a vertex, so rows and
+/// columns are grouped into classes: one per distinct vertex coordinate and
+/// one per gap between two of them. A 2D prefix sum over uncovered classes
+/// answers any rectangle in constant time.
+///
+/// A notch one tile wide has no tile of its own, so the tiles on both sides
+/// of it still form an unbroken run.
+pub struct TileGrid {
+    xs: Vec<i64>,
+    ys: Vec<i64>,
+    /// `(ys.len() * 2 + 1) * (xs.len() * 2 + 1)` prefix sums of uncovered
+    /// classes; class `2i` is the coordinate `xs[i]`, class `2i + 1` the gap
+    /// after it.
+    uncovered: Vec<u32>,
+}
+
+impl TileGrid {
+    fn new(polygon: &RectilinearPolygon) -> Self {
+        let mut xs: Vec<i64> = polygon.vertices.iter().map(|p| p.x).collect();
+        let mut ys: Vec<i64> = polygon.vertices.iter().map(|p| p.y).collect();
+        xs.sort_unstable();
+        xs.dedup();
+        ys.sort_unstable();
+        ys.dedup();
+
+        // between two consecutive ys the crossings alternate between
+        // entering and leaving the polygon
+        let between: Vec<Vec<(i64, i64)>> = ys
+            .windows(2)
+            .map(|w| {
+                let crossings = polygon.vertical_crossings(w[0], w[1]);
+                merge_runs(
+                    crossings
+                        .chunks(2)
+                        .map(|c| (c[0], c[c.len() - 1]))
+                        .collect(),
+                )
+            })
+            .collect();
+
+        // a row through a vertex is covered wherever the rows just above or
+        // below it are, plus the edges lying on it
+        let on_row: Vec<Vec<(i64, i64)>> = ys
+            .iter()
+            .enumerate()
+            .map(|(i, &y)| {
+                let mut runs: Vec<(i64, i64)> = polygon
+                    .edges()
+                    .filter(|(a, b)| a.y == y && b.y == y)
+                    .map(|(a, b)| (a.x.min(b.x), a.x.max(b.x)))
+                    .collect();
+                if i > 0 {
+                    runs.extend(&between[i - 1]);
+                }
+                if let Some(below) = between.get(i) {
+                    runs.extend(below);
+                }
+                merge_runs(runs)
+            })
+            .collect();
+
+        let columns = classes(&xs);
+        let rows = classes(&ys);
+        let row_runs: Vec<&[(i64, i64)]> = (0..ys.len())
+            .flat_map(|i| {
+                let between = between.get(i).map_or(&[][..], Vec::as_slice);
+                [on_row[i].as_slice(), between]
+            })
+            .collect();
+
+        let stride = columns.len() + 1;
+        let mut uncovered = vec![0u32; (rows.len() + 1) * stride];
+        for (r, (&(_, height), runs)) in rows.iter().zip(&row_runs).enumerate() {
+            for (c, &(x, width)) in columns.iter().enumerate() {
+                let empty = height > 0 && width > 0 && !covers(runs, x);
+                uncovered[(r + 1) * stride + c + 1] = u32::from(empty)
+                    + uncovered[r * stride + c + 1]
+                    + uncovered[(r + 1) * stride + c]
+                    - uncovered[r * stride + c];
+            }
+        }
+
+        TileGrid { xs, ys, uncovered }
+    }
+
+    /// Whether every tile in `rect` is inside or on the polygon.
+    pub fn contains_rect(&self, rect: &Rect) -> bool {
+        let (Some(x0), Some(x1)) = (
+            class_of(&self.xs, rect.min.x),
+            class_of(&self.xs, rect.max.x),
+        ) else {
+            return false;
+        };
+        let (Some(y0), Some(y1)) = (
+            class_of(&self.ys, rect.min.y),
+            class_of(&self.ys, rect.max.y),
+        ) else {
+            return false;
+        };
+
+        self.uncovered_in(y0, y1, x0, x1) == 0
+    }
+
+    /// Uncovered classes in rows `r0..=r1` and columns `c0..=c1`.
+    fn uncovered_in(&self, r0: usize, r1: usize, c0: usize, c1: usize) -> u32 {
+        let stride = self.xs.len() * 2 + 1;
+        let at = |r: usize, c: usize| self.uncovered[r * stride + c];
+        at(r1 + 1, c1 + 1) + at(r0, c0) - at(r0, c1 + 1) - at(r1 + 1, c0)
+    }
+
+    /// Every rectangle whose tiles are all inside or on the polygon and that
+    /// can't grow in any direction, with no constraint on its corners.
+    pub fn maximal_rects(&self) -> Vec<Rect> {
+        // classes holding at least one tile, as (class index, first, last)
+        let nonempty = |coords: &[i64]| -> Vec<(usize, i64, i64)> {
+            classes(coords)
+                .into_iter()
+                .enumerate()
+                .filter(|&(_, (_, len))| len > 0)
+                .map(|(k, (first, len))| (k, first, first + len - 1))
+                .collect()
+        };
+        let columns = nonempty(&self.xs);
+        let rows = nonempty(&self.ys);
+
+        // largest rectangles in a histogram, one histogram per bottom row;
+        // each rectangle popped off the stack can't grow sideways or upwards
+        let mut found = Vec::new();
+        let mut heights = vec![0usize; columns.len()];
+        let mut stack: Vec<(usize, usize)> = Vec::new();
+        for (r, &(row, _, y_max)) in rows.iter().enumerate() {
+            for (c, &(col, _, _)) in columns.iter().enumerate() {
+                let covered = self.uncovered_in(row, row, col, col) == 0;
+                heights[c] = if covered { heights[c] + 1 } else { 0 };
+            }
+
+            stack.clear();
+            for c in 0..=columns.len() {
+                let h = heights.get(c).copied().unwrap_or(0);
+                let mut start = c;
+                while let Some(&(s, top)) = stack.last() {
+                    if top < h {
+                        break;
+                    }
+                    stack.pop();
+                    start = s;
+                    if top == h {
+                        continue;
+                    }
+
+                    // and it can't grow downwards if the next row is blocked
+                    let blocked = rows.get(r + 1).is_none_or(|&(below, _, _)| {
+                        self.uncovered_in(below, below, columns[s].0, columns[c - 1].0) > 0
+                    });
+                    if blocked {
+                        found.push(Rect {
+                            min: Point::new(columns[s].1, rows[r + 1 - top].1),
+                            max: Point::new(columns[c - 1].2, y_max),
+                        });
+                    }
+                }
+                stack.push((start, h));
+            }
+        }
+
+        found
+    }
+
+    /// The `k` largest maximal rectangles by tile count, largest first.
+    pub fn largest_rects(&self, k: usize) -> Vec<Rect> {
+        let mut rects = self.maximal_rects();
+        rects.sort_by_key(|r| (std::cmp::Reverse(r.tile_count()), r.min, r.max));
+        rects.truncate(k);
+        rects
+    }
+}
+
+/// A representative coordinate and the number of tiles for every class of
+/// the sorted coordinates `coords`: each coordinate, then the gap after it.
+fn classes(coords: &[i64]) -> Vec<(i64, i64)> {
+    coords
+        .iter()
+        .enumerate()
+        .flat_map(|(i, &c)| {
+            let gap = coords
+                .get(i + 1)
+                .map_or((c + 1, 0), |&next| (c + 1, next - c - 1));
+            [(c, 1), gap]
+        })
+        .collect()
+}
+
+/// The class holding coordinate `v`, or `None` outside the polygon's extent.
+fn class_of(coords: &[i64], v: i64) -> Option<usize> {
+    let idx = coords.partition_point(|&c| c < v);
+    if coords.get(idx) == Some(&v) {
+        Some(2 * idx)
+    } else if idx > 0 && idx < coords.len() {
+        Some(2 * idx - 1)
+    } else {
+        None
+    }
+}
+
+fn covers(runs: &[(i64, i64)], x: i64) -> bool {
+    let idx = runs.partition_point(|&(lo, _)| lo <= x);
+    idx > 0 && runs[idx - 1].1 >= x
+}
+
+/// Sorts runs and merges those that overlap or sit in adjacent columns.
+fn merge_runs(mut runs: Vec<(i64, i64)>) -> Vec<(i64, i64)> {
+    runs.sort_unstable();
+    let mut merged: Vec<(i64, i64)> = Vec::with_capacity(runs.len());
+    for (lo, hi) in runs {
+        match merged.last_mut() {
+            Some(last) if lo <= last.1 + 1 => last.1 = last.1.max(hi),
+            _ => merged.push((lo, hi)),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::XorShift;
+    use itertools::Itertools;
+
+    const ROUNDS: usize = 1000;
+
+    /// Brute-force colouring: the polygon is drawn at double resolution so that
+    /// a notch one tile wide still leaves a gap for the flood fill. Even cells of
+    /// the raster are tiles, odd ones the centres of the unit squares between.
+    struct Raster {
+        min: Point,
+        width: usize,
+        height: usize,
+        outside: Vec<bool>,
+    }
+
+    impl Raster {
+        fn new(polygon: &RectilinearPolygon) -> Self {
+            let bounds = polygon.bounding_box();
+            let min = Point::new(bounds.min.x - 1, bounds.min.y - 1);
+            let width = (2 * (bounds.max.x - min.x + 1) + 1) as usize;
+            let height = (2 * (bounds.max.y - min.y + 1) + 1) as usize;
+            let cell =
+                |p: Point| (2 * (p.y - min.y)) as usize * width + (2 * (p.x - min.x)) as usize;
+
+            let mut wall = vec![false; width * height];
+            for (start, end) in polygon.edges() {
+                let (from, to) = (cell(start), cell(end));
+                let step = if start.x == end.x { width } else { 1 };
+                for c in (from.min(to)..=from.max(to)).step_by(step) {
+                    wall[c] = true;
+                }
+            }
+
+            let mut outside = vec![false; width * height];
+            let mut stack = vec![0];
+            outside[0] = true;
+            while let Some(c) = stack.pop() {
+                let (x, y) = (c % width, c / width);
+                let neighbours = [
+                    (x > 0).then(|| c - 1),
+                    (x + 1 < width).then_some(c + 1),
+                    (y > 0).then(|| c - width),
+                    (y + 1 < height).then_some(c + width),
+                ];
+                for next in neighbours.into_iter().flatten() {
+                    if !wall[next] && !outside[next] {
+                        outside[next] = true;
+                        stack.push(next);
+                    }
+                }
+            }
+
+            Raster {
+                min,
+                width,
+                height,
+                outside,
+            }
+        }
+
+        /// Whether the raster cell at doubled coordinates `(dx, dy)` is not outside.
+        fn covered(&self, dx: i64, dy: i64) -> bool {
+            let (x, y) = (dx - 2 * self.min.x, dy - 2 * self.min.y);
+            x >= 0
+                && y >= 0
+                && (x as usize) < self.width
+                && (y as usize) < self.height
+                && !self.outside[y as usize * self.width + x as usize]
+        }
+
+        fn tile(&self, p: Point) -> bool {
+            self.covered(2 * p.x, 2 * p.y)
+        }
+
+        fn tiles_in(&self, rect: &Rect) -> impl Iterator<Item = Point> {
+            (rect.min.y..=rect.max.y)
+                .flat_map(move |y| (rect.min.x..=rect.max.x).map(move |x| Point::new(x, y)))
+        }
+
+        fn contains_rect(&self, rect: &Rect) -> bool {
+            self.tiles_in(rect).all(|p| self.tile(p))
+        }
+
+        /// Tile count of the largest fully coloured rectangle within `bounds`,
+        /// trying every pair of corners against a prefix sum of uncoloured tiles.
+        fn largest_rect_area(&self, bounds: &Rect) -> i128 {
+            let w = (bounds.max.x - bounds.min.x + 1) as usize;
+            let h = (bounds.max.y - bounds.min.y + 1) as usize;
+            let mut gaps = vec![0u32; (w + 1) * (h + 1)];
+            for (k, p) in self.tiles_in(bounds).enumerate() {
+                let (x, y) = (k % w, k / w);
+                gaps[(y + 1) * (w + 1) + x + 1] = u32::from(!self.tile(p))
+                    + gaps[y * (w + 1) + x + 1]
+                    + gaps[(y + 1) * (w + 1) + x]
+                    - gaps[y * (w + 1) + x];
+            }
+            let at = |x: usize, y: usize| gaps[y * (w + 1) + x];
+
+            let mut best = 0;
+            for (y0, y1) in (0..h).tuple_combinations().chain((0..h).map(|y| (y, y))) {
+                for (x0, x1) in (0..w).tuple_combinations().chain((0..w).map(|x| (x, x))) {
+                    let empty = at(x1 + 1, y1 + 1) + at(x0, y0) - at(x0, y1 + 1) - at(x1 + 1, y0);
+                    if empty == 0 {
+                        best = best.max(((x1 - x0 + 1) * (y1 - y0 + 1)) as i128);
+                    }
+                }
+            }
+            best
+        }
+
+        /// Area of the polygon inside `rect`, counted in unit squares.
+        fn area_in(&self, rect: &Rect) -> i128 {
+            let squares = Rect {
+                min: rect.min,
+                max: Point::new(rect.max.x - 1, rect.max.y - 1),
+            };
+            self.tiles_in(&squares)
+                .filter(|p| self.covered(2 * p.x + 1, 2 * p.y + 1))
+                .count() as i128
+        }
+    }
+
+    /// A random polygon made of vertical bars of random extent standing side by
+    /// side. Bars of width one give one-tile notches, equal neighbouring bars give
+    /// collinear edges, and transposing or reversing covers the other directions.
+    fn random_polygon(rng: &mut XorShift) -> Vec<Point> {
+        let bars = rng.range(1, 6) as usize;
+        let mut xs = vec![rng.range(0, 3)];
+        for _ in 0..bars {
+            let last = *xs.last().unwrap();
+            xs.push(last + rng.range(1, 4));
+        }
+
+        let mut spans: Vec<(i64, i64)> = Vec::with_capacity(bars);
+        while spans.len() < bars {
+            let lo = rng.range(0, 10);
+            let hi = lo + rng.range(1, 8);
+            // neighbouring bars must share some height to stay connected
+            let connected = spans
+                .last()
+                .is_none_or(|&(plo, phi)| lo.max(plo) < hi.min(phi));
+            if connected {
+                spans.push((lo, hi));
+            }
+        }
+
+        let mut points = Vec::new();
+        for (i, &(_, hi)) in spans.iter().enumerate() {
+            points.push(Point::new(xs[i], hi));
+            points.push(Point::new(xs[i + 1], hi));
+        }
+        for (i, &(lo, _)) in spans.iter().enumerate().rev() {
+            points.push(Point::new(xs[i + 1], lo));
+            points.push(Point::new(xs[i], lo));
+        }
+        points.dedup();
+
+        if rng.below(2) == 1 {
+            points.iter_mut().for_each(|c| *c = Point::new(c.y, c.x));
+        }
+        if rng.below(2) == 1 {
+            points.reverse();
+        }
+        points
+    }
+
+    /// Runs `check` on `ROUNDS` random polygons along with their raster.
+    fn for_random_polygons(mut check: impl FnMut(&RectilinearPolygon, &Raster, &mut XorShift)) {
+        let mut rng = XorShift::new(0x2545_f491_4f6c_dd1d);
+        for _ in 0..ROUNDS {
+            let polygon = RectilinearPolygon::new(random_polygon(&mut rng)).unwrap();
+            let raster = Raster::new(&polygon);
+            check(&polygon, &raster, &mut rng);
+        }
+    }
+
+    /// Rectangles between every pair of vertices plus `random` ones with
+    /// corners anywhere around the polygon.
+    fn candidate_rects(
+        polygon: &RectilinearPolygon,
+        rng: &mut XorShift,
+        random: usize,
+    ) -> Vec<Rect> {
+        let mut rects: Vec<Rect> = polygon
+            .vertices()
+            .iter()
+            .tuple_combinations()
+            .map(|(&a, &b)| Rect::from_corners(a, b))
+            .collect();
+        for _ in 0..random {
+            let mut point = || Point::new(rng.range(-1, 25), rng.range(-1, 25));
+            rects.push(Rect::from_corners(point(), point()));
+        }
+        rects
+    }
+
+    fn surroundings(polygon: &RectilinearPolygon) -> Rect {
+        let bounds = polygon.bounding_box();
+        Rect::from_corners(
+            Point::new(bounds.min.x - 1, bounds.min.y - 1),
+            Point::new(bounds.max.x + 1, bounds.max.y + 1),
+        )
+    }
+
+    #[test]
+    fn rectangle_containment_matches_raster() {
+        for_random_polygons(|polygon, raster, rng| {
+            let tiles = polygon.tile_grid();
+            for rect in candidate_rects(polygon, rng, 50) {
+                assert_eq!(
+                    tiles.contains_rect(&rect),
+                    raster.contains_rect(&rect),
+                    "{rect:?} in {polygon:?}"
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn tile_count_and_location_match_raster() {
+        for_random_polygons(|polygon, raster, _| {
+            let around = surroundings(polygon);
+            let coloured = raster.tiles_in(&around).filter(|&p| raster.tile(p)).count() as i128;
+            assert_eq!(polygon.lattice_points(), coloured, "{polygon:?}");
+            for p in raster.tiles_in(&around) {
+                assert_eq!(polygon.contains(p), raster.tile(p), "{p:?} in {polygon:?}");
+            }
+        });
+    }
+
+    #[test]
+    fn maximal_rectangles_match_raster() {
+        for_random_polygons(|polygon, raster, _| {
+            let tiles = polygon.tile_grid();
+            let largest = tiles.largest_rects(1).first().map_or(0, Rect::tile_count);
+            assert_eq!(
+                largest,
+                raster.largest_rect_area(&surroundings(polygon)),
+                "{polygon:?}"
+            );
+
+            for rect in tiles.maximal_rects() {
+                let grown = [
+                    Rect {
+                        min: Point::new(rect.min.x - 1, rect.min.y),
+                        ..rect
+                    },
+                    Rect {
+                        min: Point::new(rect.min.x, rect.min.y - 1),
+                        ..rect
+                    },
+                    Rect {
+                        max: Point::new(rect.max.x + 1, rect.max.y),
+                        ..rect
+                    },
+                    Rect {
+                        max: Point::new(rect.max.x, rect.max.y + 1),
+                        ..rect
+                    },
+                ];
+                assert!(raster.contains_rect(&rect), "{rect:?} leaves {polygon:?}");
+                assert!(
+                    !grown.iter().any(|g| raster.contains_rect(g)),
+                    "{rect:?} in {polygon:?} can grow"
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn clipped_area_matches_raster() {
+        for_random_polygons(|polygon, raster, rng| {
+            for rect in candidate_rects(polygon, rng, 10).iter().rev().take(10) {
+                let pieces = polygon.clip(rect);
+                for piece in &pieces {
+                    RectilinearPolygon::new(piece.vertices().to_vec()).unwrap();
+                }
+                let area: i128 = pieces.iter().map(RectilinearPolygon::area).sum();
+                assert_eq!(
+                    area,
+                    raster.area_in(rect),
+                    "{polygon:?} clipped to {rect:?}"
+                );
+            }
+        });
+    }
+}
@@ -0,0 +1,371 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use itertools::Itertools;
+
+/// A fixed-length vector over GF(2), packed into `u64` words.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BitVec {
+    len: usize,
+    words: Vec<u64>,
+}
+
+impl BitVec {
+    pub fn new(len: usize) -> Self {
+        BitVec {
+            len,
+            words: vec![0; len.div_ceil(64)],
+        }
+    }
+
+    /// A vector with exactly the bits in `indices` set. Panics if an index is
+    /// out of range.
+    pub fn from_indices(len: usize, indices: impl IntoIterator<Item = usize>) -> Self {
+        let mut v = BitVec::new(len);
+        for i in indices {
+            v.set(i, true);
+        }
+        v
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, i: usize) -> bool {
+        assert!(i < self.len, "bit {i} out of range for length {}", self.len);
+        self.words[i / 64] >> (i % 64) & 1 == 1
+    }
+
+    pub fn set(&mut self, i: usize, value: bool) {
+        assert!(i < self.len, "bit {i} out of range for length {}", self.len);
+        if value {
+            self.words[i / 64] |= 1 << (i % 64);
+        } else {
+            self.words[i / 64] &= !(1 << (i % 64));
+        }
+    }
+
+    pub fn toggle(&mut self, i: usize) {
+        assert!(i < self.len, "bit {i} out of range for length {}", self.len);
+        self.words[i / 64] ^= 1 << (i % 64);
+    }
+
+    /// Adds `other` to this vector, which over GF(2) is a bitwise xor.
+    pub fn xor_assign(&mut self, other: &BitVec) {
+        assert_eq!(self.len, other.len, "length mismatch");
+        for (a, b) in self.words.iter_mut().zip(&other.words) {
+            *a ^= b;
+        }
+    }
+
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.words.iter().all(|&w| w == 0)
+    }
+
+    /// Indices of the set bits, in increasing order.
+    pub fn ones(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(w, &word)| {
+            let mut rest = word;
+            std::iter::from_fn(move || {
+                (rest != 0).then(|| {
+                    let bit = rest.trailing_zeros() as usize;
+                    rest &= rest - 1;
+                    w * 64 + bit
+                })
+            })
+        })
+    }
+}
+
+/// Returned when proving a minimum would take more work than allowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchTooLarge {
+    pub nullity: usize,
+    pub variables: usize,
+}
+
+impl fmt::Display for SearchTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "minimum-weight search over {} variables with a {}-dimensional null space is too large",
+            self.variables, self.nullity
+        )
+    }
+}
+
+impl std::error::Error for SearchTooLarge {}
+
+/// The solution set of `A x = b` over GF(2): one particular solution plus a
+/// basis of the null space of `A`.
+#[derive(Clone, Debug)]
+pub struct SolutionSpace {
+    pub particular: BitVec,
+    pub null_basis: Vec<BitVec>,
+}
+
+impl SolutionSpace {
+    /// Solves the system whose `j`-th column is `columns[j]` for the right-hand
+    /// side `target`, returning `None` when it is inconsistent.
+    pub fn new(columns: &[BitVec], target: &BitVec) -> Option<Self> {
+        let vars = columns.len();
+        let equations = target.len();
+
+        // row i holds the coefficients of equation i, with the rhs in bit `vars`
+        let mut rows: Vec<BitVec> = (0..equations)
+            .map(|i| {
+                let mut row = BitVec::new(vars + 1);
+                for (j, column) in columns.iter().enumerate() {
+                    assert_eq!(column.len(), equations, "column {j} has the wrong length");
+                    if column.get(i) {
+                        row.set(j, true);
+                    }
+                }
+                row.set(vars, target.get(i));
+                row
+            })
+            .collect();
+
+        let mut pivots = Vec::new();
+        for col in 0..vars {
+            let r = pivots.len();
+            let Some(k) = (r..rows.len()).find(|&k| rows[k].get(col)) else {
+                continue;
+            };
+            rows.swap(r, k);
+            let pivot_row = rows[r].clone();
+            for (k, row) in rows.iter_mut().enumerate() {
+                if k != r && row.get(col) {
+                    row.xor_assign(&pivot_row);
+                }
+            }
+            pivots.push(col);
+        }
+
+        // leftover rows read `0 = rhs`
+        if rows[pivots.len()..].iter().any(|row| row.get(vars)) {
+            return None;
+        }
+
+        let mut particular = BitVec::new(vars);
+        for (row, &col) in rows.iter().zip(&pivots) {
+            particular.set(col, row.get(vars));
+        }
+
+        let mut is_pivot = vec![false; vars];
+        pivots.iter().for_each(|&p| is_pivot[p] = true);
+        let null_basis = (0..vars)
+            .filter(|&f| !is_pivot[f])
+            .map(|f| {
+                let mut v = BitVec::new(vars);
+                v.set(f, true);
+                for (row, &col) in rows.iter().zip(&pivots) {
+                    if row.get(f) {
+                        v.set(col, true);
+                    }
+                }
+                v
+            })
+            .collect();
+
+        Some(SolutionSpace {
+            particular,
+            null_basis,
+        })
+    }
+
+    pub fn nullity(&self) -> usize {
+        self.null_basis.len()
+    }
+}
+
+/// Null spaces up to this dimension are enumerated outright.
+const GRAY_CODE_LIMIT: usize = 24;
+/// Largest syndrome table the meet-in-the-middle search may build.
+const TABLE_LIMIT: usize = 1 << 22;
+
+/// The solution of `A x = b` with the fewest set bits, or `None` when there is
+/// no solution. `A` is given by its columns.
+///
+/// Small null spaces are walked in Gray-code order, one xor per step. Larger
+/// ones switch to a meet-in-the-middle over the columns: every solution of
+/// weight `w` splits into two halves of at most `ceil(w / 2)` columns whose
+/// sums meet, so tables of growing half-weight are built until the best
+/// solution found is proven minimal.
+pub fn min_weight_solution(
+    columns: &[BitVec],
+    target: &BitVec,
+) -> Result<Option<BitVec>, SearchTooLarge> {
+    let Some(space) = SolutionSpace::new(columns, target) else {
+        return Ok(None);
+    };
+
+    if space.nullity() <= GRAY_CODE_LIMIT {
+        return Ok(Some(gray_code_minimum(&space)));
+    }
+    let nullity = space.nullity();
+    meet_in_the_middle(columns, target, space.particular, nullity).map(Some)
+}
+
+fn gray_code_minimum(space: &SolutionSpace) -> BitVec {
+    let mut current = space.particular.clone();
+    let mut best = current.clone();
+    let mut best_weight = best.count_ones();
+
+    for step in 1u64..1 << space.nullity() {
+        current.xor_assign(&space.null_basis[step.trailing_zeros() as usize]);
+        let weight = current.count_ones();
+        if weight < best_weight {
+            best_weight = weight;
+            best = current.clone();
+        }
+    }
+
+    best
+}
+
+fn meet_in_the_middle(
+    columns: &[BitVec],
+    target: &BitVec,
+    particular: BitVec,
+    nullity: usize,
+) -> Result<BitVec, SearchTooLarge> {
+    let vars = columns.len();
+    let mut best = particular;
+    let mut table: HashMap<BitVec, Vec<usize>> = HashMap::new();
+
+    let sum = |subset: &[usize]| {
+        let mut s = BitVec::new(target.len());
+        subset.iter().for_each(|&j| s.xor_assign(&columns[j]));
+        s
+    };
+
+    let mut half = 0;
+    // every solution lighter than `best` has been seen once `best <= 2 * half + 1`
+    loop {
+        for subset in (0..vars).combinations(half) {
+            if table.len() >= TABLE_LIMIT {
+                return Err(SearchTooLarge {
+                    nullity,
+                    variables: vars,
+                });
+            }
+            table.entry(sum(&subset)).or_insert(subset);
+        }
+
+        for subset in (0..vars).combinations(half) {
+            let mut wanted = sum(&subset);
+            wanted.xor_assign(target);
+            if let Some(other) = table.get(&wanted) {
+                let mut candidate = BitVec::from_indices(vars, subset.iter().copied());
+                other.iter().for_each(|&j| candidate.toggle(j));
+                if candidate.count_ones() < best.count_ones() {
+                    best = candidate;
+                }
+            }
+        }
+
+        if best.count_ones() <= 2 * half + 1 {
+            return Ok(best);
+        }
+        half += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::XorShift;
+
+    const ROUNDS: usize = 300;
+
+    fn random_bits(rng: &mut XorShift, len: usize) -> BitVec {
+        BitVec::from_indices(len, (0..len).filter(|_| rng.below(2) == 1))
+    }
+
+    fn random_system(rng: &mut XorShift, vars: usize, equations: usize) -> (Vec<BitVec>, BitVec) {
+        let columns = (0..vars).map(|_| random_bits(rng, equations)).collect();
+        (columns, random_bits(rng, equations))
+    }
+
+    fn product(columns: &[BitVec], x: &BitVec) -> BitVec {
+        let mut sum = BitVec::new(columns.first().map_or(0, BitVec::len));
+        x.ones().for_each(|j| sum.xor_assign(&columns[j]));
+        sum
+    }
+
+    /// Weight of the lightest solution, trying every subset of columns in
+    /// order of size.
+    fn brute_force(columns: &[BitVec], target: &BitVec) -> Option<usize> {
+        (0..=columns.len()).find(|&weight| {
+            (0..columns.len()).combinations(weight).any(|subset| {
+                product(columns, &BitVec::from_indices(columns.len(), subset)) == *target
+            })
+        })
+    }
+
+    fn check(columns: &[BitVec], target: &BitVec, found: Option<BitVec>) {
+        match (found, brute_force(columns, target)) {
+            (Some(x), Some(weight)) => {
+                assert_eq!(product(columns, &x), *target, "not a solution");
+                assert_eq!(x.count_ones(), weight, "not the lightest solution");
+            }
+            (None, None) => {}
+            (found, expected) => panic!("found {found:?}, expected weight {expected:?}"),
+        }
+    }
+
+    #[test]
+    fn min_weight_solution_matches_brute_force() {
+        let mut rng = XorShift::new(0x2545_f491_4f6c_dd1d);
+        for _ in 0..ROUNDS {
+            let vars = rng.range(1, 12) as usize;
+            let equations = rng.range(1, 8) as usize;
+            let (columns, target) = random_system(&mut rng, vars, equations);
+            check(
+                &columns,
+                &target,
+                min_weight_solution(&columns, &target).unwrap(),
+            );
+        }
+    }
+
+    #[test]
+    fn meet_in_the_middle_matches_brute_force() {
+        let mut rng = XorShift::new(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..ROUNDS {
+            let vars = rng.range(1, 12) as usize;
+            let equations = rng.range(1, 8) as usize;
+            let (columns, target) = random_system(&mut rng, vars, equations);
+            let found = SolutionSpace::new(&columns, &target).map(|space| {
+                let nullity = space.nullity();
+                meet_in_the_middle(&columns, &target, space.particular, nullity).unwrap()
+            });
+            check(&columns, &target, found);
+        }
+    }
+
+    #[test]
+    fn large_null_space_uses_meet_in_the_middle() {
+        let mut rng = XorShift::new(0x0123_4567_89ab_cdef);
+        for _ in 0..5 {
+            let (columns, target) = random_system(&mut rng, 40, 15);
+            let space = SolutionSpace::new(&columns, &target).unwrap();
+            assert!(space.nullity() > GRAY_CODE_LIMIT);
+            check(
+                &columns,
+                &target,
+                min_weight_solution(&columns, &target).unwrap(),
+            );
+        }
+    }
+}
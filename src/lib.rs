@@ -1,3 +1,11 @@
+pub mod counting;
+pub mod disjoint_set;
+pub mod geometry;
+pub mod gf2;
+pub mod packing;
+pub mod rng;
+pub mod spatial;
+
 use std::fs;
 use std::path::Path;
 
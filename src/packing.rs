@@ -0,0 +1,326 @@
+use std::time::{Duration, Instant};
+
+/// A polyomino, stored as its cells sorted row by row and shifted so the
+/// smallest `x` and `y` are both 0.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Shape {
+    cells: Vec<(usize, usize)>,
+}
+
+impl Shape {
+    /// The shape made of the `(x, y)` cells, in any order and position.
+    pub fn new(cells: impl IntoIterator<Item = (usize, usize)>) -> Self {
+        let mut cells: Vec<(usize, usize)> = cells.into_iter().collect();
+        let min_x = cells.iter().map(|&(x, _)| x).min().unwrap_or(0);
+        let min_y = cells.iter().map(|&(_, y)| y).min().unwrap_or(0);
+        cells
+            .iter_mut()
+            .for_each(|(x, y)| (*x, *y) = (*x - min_x, *y - min_y));
+        cells.sort_unstable_by_key(|&(x, y)| (y, x));
+        cells.dedup();
+        Shape { cells }
+    }
+
+    pub fn cells(&self) -> &[(usize, usize)] {
+        &self.cells
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn width(&self) -> usize {
+        self.cells.iter().map(|&(x, _)| x + 1).max().unwrap_or(0)
+    }
+
+    pub fn height(&self) -> usize {
+        self.cells.last().map_or(0, |&(_, y)| y + 1)
+    }
+
+    /// Quarter turn clockwise.
+    fn rotated(&self) -> Shape {
+        let h = self.height();
+        Shape::new(self.cells.iter().map(|&(x, y)| (h - 1 - y, x)))
+    }
+
+    fn mirrored(&self) -> Shape {
+        let w = self.width();
+        Shape::new(self.cells.iter().map(|&(x, y)| (w - 1 - x, y)))
+    }
+
+    /// The distinct rotations and reflections of the shape, starting with
+    /// the shape itself.
+    pub fn orientations(&self) -> Vec<Shape> {
+        let mut result: Vec<Shape> = Vec::with_capacity(8);
+        for start in [self.clone(), self.mirrored()] {
+            let mut shape = start;
+            for _ in 0..4 {
+                let next = shape.rotated();
+                if !result.contains(&shape) {
+                    result.push(shape);
+                }
+                shape = next;
+            }
+        }
+        result
+    }
+}
+
+/// A piece of shape `shape`, turned into `shape.orientations()[orientation]`,
+/// with the top-left corner of its bounding box at `(x, y)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Placement {
+    pub shape: usize,
+    pub orientation: usize,
+    pub x: usize,
+    pub y: usize,
+}
+
+impl Placement {
+    /// The region cells covered by the piece.
+    pub fn cells(&self, shapes: &[Shape]) -> Vec<(usize, usize)> {
+        shapes[self.shape].orientations()[self.orientation]
+            .cells()
+            .iter()
+            .map(|&(dx, dy)| (self.x + dx, self.y + dy))
+            .collect()
+    }
+}
+
+/// Limits on the work [`pack_within`] may do; `None` means no limit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Budget {
+    /// Most search nodes (pieces placed or cells left empty) to visit.
+    pub nodes: Option<u64>,
+    pub time: Option<Duration>,
+}
+
+/// The outcome of [`pack_within`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Packing {
+    Fits(Vec<Placement>),
+    DoesNotFit,
+    /// The budget ran out before the search could tell.
+    Unknown,
+}
+
+/// Places `counts[i]` copies of `shapes[i]` into a `width` × `height` region
+/// without overlaps, turning and flipping pieces as needed. Cells may stay
+/// empty. Returns one packing, or `None` when there is none.
+pub fn pack(
+    width: usize,
+    height: usize,
+    shapes: &[Shape],
+    counts: &[usize],
+) -> Option<Vec<Placement>> {
+    match pack_within(width, height, shapes, counts, Budget::default()) {
+        Packing::Fits(placements) => Some(placements),
+        Packing::DoesNotFit => None,
+        Packing::Unknown => unreachable!("an unlimited search always finishes"),
+    }
+}
+
+/// Like [`pack`], but gives up with [`Packing::Unknown`] once `budget` is
+/// spent.
+///
+/// The search fills the region in reading order: the first undecided cell is
+/// either covered by a piece whose first cell lands on it, or left empty.
+/// Since every piece is tried at its first cell only, each packing is found
+/// once, and a branch is cut as soon as the pieces left need more cells than
+/// remain undecided.
+pub fn pack_within(
+    width: usize,
+    height: usize,
+    shapes: &[Shape],
+    counts: &[usize],
+    budget: Budget,
+) -> Packing {
+    assert_eq!(shapes.len(), counts.len(), "one count per shape");
+    let mut packer = Packer::new(width, height, shapes, counts, budget);
+    match packer.search(0) {
+        Ok(true) => Packing::Fits(packer.placed),
+        Ok(false) => Packing::DoesNotFit,
+        Err(OutOfBudget) => Packing::Unknown,
+    }
+}
+
+/// The clock is only read once per this many nodes.
+const CLOCK_INTERVAL: u64 = 1 << 12;
+
+struct OutOfBudget;
+
+/// A shape orientation, with its cells relative to the top-left corner.
+struct Orientation {
+    /// Column of the first cell in reading order, so a piece anchored on a
+    /// cell starts `lead` columns left of it.
+    lead: usize,
+    width: usize,
+    height: usize,
+    offsets: Vec<(usize, usize)>,
+}
+
+struct Packer {
+    width: usize,
+    height: usize,
+    /// Cells already covered or left empty.
+    decided: Vec<bool>,
+    /// Undecided cells, all at or after the search position.
+    open: usize,
+    /// Cells the remaining pieces still need.
+    needed: usize,
+    remaining: Vec<usize>,
+    orientations: Vec<Vec<Orientation>>,
+    sizes: Vec<usize>,
+    placed: Vec<Placement>,
+    budget: Budget,
+    nodes: u64,
+    started: Instant,
+}
+
+impl Packer {
+    fn new(
+        width: usize,
+        height: usize,
+        shapes: &[Shape],
+        counts: &[usize],
+        budget: Budget,
+    ) -> Self {
+        let orientations = shapes
+            .iter()
+            .map(|shape| {
+                shape
+                    .orientations()
+                    .iter()
+                    .map(|o| {
+                        let lead = o.cells().first().map_or(0, |&(x, _)| x);
+                        Orientation {
+                            lead,
+                            width: o.width(),
+                            height: o.height(),
+                            offsets: o.cells().to_vec(),
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let sizes: Vec<usize> = shapes.iter().map(Shape::len).collect();
+        Packer {
+            width,
+            height,
+            decided: vec![false; width * height],
+            open: width * height,
+            needed: sizes.iter().zip(counts).map(|(s, c)| s * c).sum(),
+            remaining: counts.to_vec(),
+            orientations,
+            sizes,
+            placed: Vec::new(),
+            budget,
+            nodes: 0,
+            started: Instant::now(),
+        }
+    }
+
+    /// Counts a node against the budget.
+    fn spend(&mut self) -> Result<(), OutOfBudget> {
+        self.nodes += 1;
+        if self.budget.nodes.is_some_and(|limit| self.nodes > limit) {
+            return Err(OutOfBudget);
+        }
+        if let Some(limit) = self.budget.time
+            && self.nodes.is_multiple_of(CLOCK_INTERVAL)
+            && self.started.elapsed() > limit
+        {
+            return Err(OutOfBudget);
+        }
+        Ok(())
+    }
+
+    fn search(&mut self, mut pos: usize) -> Result<bool, OutOfBudget> {
+        if self.needed == 0 {
+            return Ok(true);
+        }
+        self.spend()?;
+        while pos < self.decided.len() && self.decided[pos] {
+            pos += 1;
+        }
+        if self.needed > self.open {
+            return Ok(false);
+        }
+        let (ax, ay) = (pos % self.width, pos / self.width);
+
+        for shape in 0..self.orientations.len() {
+            if self.remaining[shape] == 0 {
+                continue;
+            }
+            for orientation in 0..self.orientations[shape].len() {
+                let Some((x, y)) = self.fits(shape, orientation, ax, ay) else {
+                    continue;
+                };
+                self.set(shape, orientation, x, y, true);
+                self.placed.push(Placement {
+                    shape,
+                    orientation,
+                    x,
+                    y,
+                });
+                if self.search(pos + 1)? {
+                    return Ok(true);
+                }
+                self.placed.pop();
+                self.set(shape, orientation, x, y, false);
+            }
+        }
+
+        // leave the cell empty, if the pieces can spare it
+        if self.needed < self.open {
+            self.decided[pos] = true;
+            self.open -= 1;
+            if self.search(pos + 1)? {
+                return Ok(true);
+            }
+            self.decided[pos] = false;
+            self.open += 1;
+        }
+        Ok(false)
+    }
+
+    /// The corner at which the orientation covers `(ax, ay)` with its first
+    /// cell, if it stays inside the region and only covers undecided cells.
+    fn fits(
+        &self,
+        shape: usize,
+        orientation: usize,
+        ax: usize,
+        ay: usize,
+    ) -> Option<(usize, usize)> {
+        let o = &self.orientations[shape][orientation];
+        let x = ax.checked_sub(o.lead)?;
+        if x + o.width > self.width || ay + o.height > self.height {
+            return None;
+        }
+        o.offsets
+            .iter()
+            .all(|&(dx, dy)| !self.decided[(ay + dy) * self.width + x + dx])
+            .then_some((x, ay))
+    }
+
+    fn set(&mut self, shape: usize, orientation: usize, x: usize, y: usize, covered: bool) {
+        for &(dx, dy) in &self.orientations[shape][orientation].offsets {
+            self.decided[(y + dy) * self.width + x + dx] = covered;
+        }
+        let size = self.sizes[shape];
+        if covered {
+            self.remaining[shape] -= 1;
+            self.needed -= size;
+            self.open -= size;
+        } else {
+            self.remaining[shape] += 1;
+            self.needed += size;
+            self.open += size;
+        }
+    }
+}
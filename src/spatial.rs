@@ -0,0 +1,379 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::disjoint_set::DisjointSet;
+
+/// Two point indices (`a < b`) and their squared distance. The derived order
+/// sorts by distance and then by index, so it is total and deterministic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Pair {
+    pub dist_sq: i128,
+    pub a: usize,
+    pub b: usize,
+}
+
+impl Pair {
+    fn new(dist_sq: i128, i: usize, j: usize) -> Self {
+        Pair {
+            dist_sq,
+            a: i.min(j),
+            b: i.max(j),
+        }
+    }
+}
+
+/// Squared Euclidean distance, exact for any `i64` coordinates.
+pub fn distance_squared<const D: usize>(p: &[i64; D], q: &[i64; D]) -> i128 {
+    p.iter()
+        .zip(q)
+        .map(|(&a, &b)| {
+            let d = a as i128 - b as i128;
+            d * d
+        })
+        .sum()
+}
+
+/// A static k-d tree over integer points, stored implicitly: the node for the
+/// range `lo..hi` of `order` is the point at the middle of that range.
+pub struct KdTree<const D: usize> {
+    points: Vec<[i64; D]>,
+    order: Vec<usize>,
+}
+
+impl<const D: usize> KdTree<D> {
+    pub fn new(points: Vec<[i64; D]>) -> Self {
+        let mut order: Vec<usize> = (0..points.len()).collect();
+        build(&points, &mut order, 0);
+        KdTree { points, order }
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn point(&self, idx: usize) -> &[i64; D] {
+        &self.points[idx]
+    }
+
+    /// The `k` points closest to point `idx` (excluding itself) as
+    /// `(squared distance, index)`, closest first.
+    pub fn nearest(&self, idx: usize, k: usize) -> Vec<(i128, usize)> {
+        if k == 0 {
+            return Vec::new();
+        }
+        let mut query = Query::new(idx, k, None);
+        self.visit(0, self.order.len(), 0, &mut query, &|_| true, &|_| false);
+        query.into_sorted()
+    }
+
+    /// Every pair of points, streamed lazily in increasing [`Pair`] order.
+    pub fn nearest_pairs(&self) -> NearestPairs<'_, D> {
+        NearestPairs::new(self)
+    }
+
+    /// Euclidean minimum spanning tree built with Borůvka's algorithm. Ties
+    /// are broken by the [`Pair`] order, so the tree is unique and contains
+    /// exactly the edges Kruskal's algorithm would pick from
+    /// [`nearest_pairs`](Self::nearest_pairs).
+    pub fn minimum_spanning_tree(&self) -> Vec<Pair> {
+        let n = self.points.len();
+        let mut components = DisjointSet::new(n);
+        let mut edges: Vec<Pair> = Vec::with_capacity(n.saturating_sub(1));
+
+        while components.component_count() > 1 {
+            let comp: Vec<usize> = (0..n).map(|i| components.find(i)).collect();
+            let mut labels = vec![Label::Empty; n];
+            self.label_subtrees(0, n, &comp, &mut labels);
+
+            let mut best: Vec<Option<Pair>> = vec![None; n];
+            for i in 0..n {
+                let c = comp[i];
+                let limit = best[c].map(|p| p.dist_sq);
+                let mut query = Query::new(i, 1, limit);
+                self.visit(0, n, 0, &mut query, &|j| comp[j] != c, &|pos| {
+                    labels[pos] == Label::Uniform(c)
+                });
+                if let Some(&(d, j)) = query.into_sorted().first() {
+                    let candidate = Pair::new(d, i, j);
+                    if best[c].is_none_or(|b| candidate < b) {
+                        best[c] = Some(candidate);
+                    }
+                }
+            }
+
+            for pair in best.into_iter().flatten() {
+                if components.union(pair.a, pair.b) {
+                    edges.push(pair);
+                }
+            }
+        }
+
+        edges
+    }
+
+    fn visit(
+        &self,
+        lo: usize,
+        hi: usize,
+        depth: usize,
+        query: &mut Query,
+        accept: &dyn Fn(usize) -> bool,
+        skip: &dyn Fn(usize) -> bool,
+    ) {
+        if lo >= hi {
+            return;
+        }
+        let mid = (lo + hi) / 2;
+        if skip(mid) {
+            return;
+        }
+
+        let target = &self.points[query.idx];
+        let p = self.order[mid];
+        if p != query.idx && accept(p) {
+            query.offer(distance_squared(target, &self.points[p]), p);
+        }
+
+        let axis = depth % D;
+        let diff = target[axis] as i128 - self.points[p][axis] as i128;
+        let (near, far) = if diff < 0 {
+            ((lo, mid), (mid + 1, hi))
+        } else {
+            ((mid + 1, hi), (lo, mid))
+        };
+
+        self.visit(near.0, near.1, depth + 1, query, accept, skip);
+        if query.admits(diff * diff) {
+            self.visit(far.0, far.1, depth + 1, query, accept, skip);
+        }
+    }
+
+    fn label_subtrees(&self, lo: usize, hi: usize, comp: &[usize], labels: &mut [Label]) -> Label {
+        if lo >= hi {
+            return Label::Empty;
+        }
+        let mid = (lo + hi) / 2;
+        let left = self.label_subtrees(lo, mid, comp, labels);
+        let right = self.label_subtrees(mid + 1, hi, comp, labels);
+        let label = Label::Uniform(comp[self.order[mid]])
+            .merge(left)
+            .merge(right);
+        labels[mid] = label;
+        label
+    }
+}
+
+fn build<const D: usize>(points: &[[i64; D]], order: &mut [usize], depth: usize) {
+    if order.len() <= 1 {
+        return;
+    }
+    let axis = depth % D;
+    let mid = order.len() / 2;
+    order.select_nth_unstable_by_key(mid, |&i| points[i][axis]);
+    let (left, right) = order.split_at_mut(mid);
+    build(points, left, depth + 1);
+    build(points, &mut right[1..], depth + 1);
+}
+
+/// Which component every point in a subtree belongs to, if they all agree.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Label {
+    Empty,
+    Uniform(usize),
+    Mixed,
+}
+
+impl Label {
+    fn merge(self, other: Label) -> Label {
+        match (self, other) {
+            (Label::Empty, l) | (l, Label::Empty) => l,
+            (Label::Uniform(a), Label::Uniform(b)) if a == b => self,
+            _ => Label::Mixed,
+        }
+    }
+}
+
+/// Bounded max-heap of the best candidates seen so far.
+struct Query {
+    idx: usize,
+    k: usize,
+    limit: Option<i128>,
+    best: BinaryHeap<(i128, usize)>,
+}
+
+impl Query {
+    fn new(idx: usize, k: usize, limit: Option<i128>) -> Self {
+        Query {
+            idx,
+            k,
+            limit,
+            best: BinaryHeap::with_capacity(k + 1),
+        }
+    }
+
+    fn offer(&mut self, dist_sq: i128, idx: usize) {
+        if self.k == 0 || self.limit.is_some_and(|l| dist_sq > l) {
+            return;
+        }
+        if self.best.len() < self.k {
+            self.best.push((dist_sq, idx));
+        } else if (dist_sq, idx) < *self.best.peek().unwrap() {
+            self.best.pop();
+            self.best.push((dist_sq, idx));
+        }
+    }
+
+    /// Whether a subtree at least `plane_sq` away could still improve the result.
+    fn admits(&self, plane_sq: i128) -> bool {
+        if self.limit.is_some_and(|l| plane_sq > l) {
+            return false;
+        }
+        self.best.len() < self.k || self.best.peek().is_some_and(|&(d, _)| plane_sq <= d)
+    }
+
+    fn into_sorted(self) -> Vec<(i128, usize)> {
+        self.best.into_sorted_vec()
+    }
+}
+
+/// Iterator over all pairs in increasing [`Pair`] order. Each point keeps a
+/// buffer of its nearest neighbours, doubled on demand, and the buffers are
+/// merged through a heap.
+pub struct NearestPairs<'a, const D: usize> {
+    tree: &'a KdTree<D>,
+    neighbours: Vec<Vec<(i128, usize)>>,
+    cursor: Vec<usize>,
+    heap: BinaryHeap<Reverse<(Pair, usize)>>,
+}
+
+impl<'a, const D: usize> NearestPairs<'a, D> {
+    const INITIAL_NEIGHBOURS: usize = 8;
+
+    fn new(tree: &'a KdTree<D>) -> Self {
+        let n = tree.len();
+        let k = Self::INITIAL_NEIGHBOURS.min(n.saturating_sub(1));
+        let neighbours: Vec<Vec<(i128, usize)>> = (0..n).map(|i| tree.nearest(i, k)).collect();
+
+        let mut heap = BinaryHeap::with_capacity(n);
+        for (i, list) in neighbours.iter().enumerate() {
+            if let Some(&(d, j)) = list.first() {
+                heap.push(Reverse((Pair::new(d, i, j), i)));
+            }
+        }
+
+        NearestPairs {
+            tree,
+            neighbours,
+            cursor: vec![0; n],
+            heap,
+        }
+    }
+
+    fn advance(&mut self, owner: usize) {
+        self.cursor[owner] += 1;
+        let pos = self.cursor[owner];
+        let available = self.tree.len() - 1;
+
+        if pos == self.neighbours[owner].len() && pos < available {
+            let k = (pos * 2).min(available);
+            self.neighbours[owner] = self.tree.nearest(owner, k);
+        }
+
+        if let Some(&(d, j)) = self.neighbours[owner].get(pos) {
+            self.heap.push(Reverse((Pair::new(d, owner, j), owner)));
+        }
+    }
+}
+
+impl<const D: usize> Iterator for NearestPairs<'_, D> {
+    type Item = Pair;
+
+    fn next(&mut self) -> Option<Pair> {
+        // every pair shows up in the buffers of both endpoints; only the
+        // lower index reports it
+        while let Some(Reverse((pair, owner))) = self.heap.pop() {
+            self.advance(owner);
+            if owner == pair.a {
+                return Some(pair);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::XorShift;
+
+    const ROUNDS: usize = 500;
+
+    /// Small point sets on a tiny grid, so duplicate points and equal
+    /// distances are common; the first round has a single point.
+    fn for_random_trees(mut check: impl FnMut(&KdTree<3>)) {
+        let mut rng = XorShift::new(0x9e37_79b9_7f4a_7c15);
+        for round in 0..ROUNDS {
+            let n = if round == 0 {
+                1
+            } else {
+                rng.range(1, 14) as usize
+            };
+            let points = (0..n)
+                .map(|_| [rng.range(0, 3), rng.range(0, 3), rng.range(-1, 1)])
+                .collect();
+            check(&KdTree::new(points));
+        }
+    }
+
+    fn all_pairs(tree: &KdTree<3>) -> Vec<Pair> {
+        let n = tree.len();
+        let mut pairs: Vec<Pair> = (0..n)
+            .flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+            .map(|(i, j)| Pair::new(distance_squared(tree.point(i), tree.point(j)), i, j))
+            .collect();
+        pairs.sort_unstable();
+        pairs
+    }
+
+    #[test]
+    fn nearest_matches_brute_force() {
+        for_random_trees(|tree| {
+            for i in 0..tree.len() {
+                let mut expected: Vec<(i128, usize)> = (0..tree.len())
+                    .filter(|&j| j != i)
+                    .map(|j| (distance_squared(tree.point(i), tree.point(j)), j))
+                    .collect();
+                expected.sort_unstable();
+                for k in 0..=tree.len() {
+                    let want = &expected[..k.min(expected.len())];
+                    assert_eq!(tree.nearest(i, k), want, "point {i}, k = {k}");
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn nearest_pairs_match_sorted_pairs() {
+        for_random_trees(|tree| {
+            assert_eq!(tree.nearest_pairs().collect::<Vec<_>>(), all_pairs(tree));
+        });
+    }
+
+    #[test]
+    fn minimum_spanning_tree_matches_kruskal() {
+        for_random_trees(|tree| {
+            let mut components = DisjointSet::new(tree.len());
+            let expected: Vec<Pair> = all_pairs(tree)
+                .into_iter()
+                .filter(|p| components.union(p.a, p.b))
+                .collect();
+            let mut edges = tree.minimum_spanning_tree();
+            edges.sort_unstable();
+            assert_eq!(edges, expected);
+        });
+    }
+}